@@ -1,16 +1,26 @@
-use home;
 use std::{
     fs::{self, OpenOptions},
+    io::{self, Write},
     path::PathBuf,
 };
 use structopt::StructOpt;
 
+use crate::config::parse_config;
+use crate::storage::Storage;
+use crate::utils::frecency::rank;
+use crate::utils::path::path_to_bytes;
+
 /// Command-line arguments struct
 #[derive(Debug, StructOpt)]
 pub struct CliArgs {
     /// Path to the configuration file
     #[structopt(long)]
     pub config_path: Option<PathBuf>,
+
+    /// Print the visited dir that best matches the terms and exit,
+    /// e.g. `cd "$(fe --query proj)"`
+    #[structopt(long, min_values = 1)]
+    pub query: Option<Vec<String>>,
 }
 
 pub fn parse_args() -> CliArgs {
@@ -25,8 +35,73 @@ pub fn parse_args() -> CliArgs {
     return args;
 }
 
+// prints the visited dir that best matches the terms, without opening
+// the window. Returns the exit code.
+pub fn print_query(args: &CliArgs, terms: &[String]) -> i32 {
+    let config_path = match &args.config_path {
+        Some(config_path) => config_path,
+        None => {
+            eprintln!("no config file to read");
+            return 1;
+        }
+    };
+    let config = match parse_config(config_path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("cannot parse config file {:?}: {:?}", config_path, err);
+            return 1;
+        }
+    };
+    let data_path: PathBuf = config.data_dir.expect("data_path is empty").into();
+    let storage = match Storage::new(data_path) {
+        Ok(storage) => storage,
+        Err(err) => {
+            eprintln!("cannot read visited dirs: {:?}", err);
+            return 1;
+        }
+    };
+    let visits = match storage.list_visits() {
+        Ok(visits) => visits,
+        Err(err) => {
+            eprintln!("cannot read visited dirs: {:?}", err);
+            return 1;
+        }
+    };
+
+    // only the best matches are checked, so a stale mount elsewhere
+    // doesn't block the query. Missing dirs are forgotten.
+    let found = rank(visits, terms, chrono::Utc::now().timestamp())
+        .into_iter()
+        .find(|visit| {
+            if visit.path.is_dir() {
+                return true;
+            }
+            let _ = storage.remove_visit(&visit.path);
+            return false;
+        });
+    return match found {
+        Some(visit) => {
+            // the raw bytes, so that `cd "$(fe --query ..)"` works with
+            // names that aren't valid UTF-8
+            let mut line = path_to_bytes(&visit.path);
+            line.push(b'\n');
+            match io::stdout().write_all(&line) {
+                Ok(()) => 0,
+                Err(err) => {
+                    eprintln!("cannot print {:?}: {:?}", visit.path, err);
+                    1
+                }
+            }
+        }
+        None => {
+            eprintln!("no visited dir matches {:?}", terms.join(" "));
+            1
+        }
+    };
+}
+
 pub fn ensure_default(default_config: &PathBuf) {
-    println!("creating {:?}", default_config);
+    eprintln!("creating {:?}", default_config);
 
     // Create parent directories if they don't exist
     if let Some(parent) = default_config.parent() {
@@ -40,9 +115,9 @@ pub fn ensure_default(default_config: &PathBuf) {
         .open(default_config);
 
     match file {
-        Ok(_) => println!("Config file created: {:?}", default_config),
+        Ok(_) => eprintln!("Config file created: {:?}", default_config),
         Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
-            println!("Config file already exists: {:?}", default_config)
+            eprintln!("Config file already exists: {:?}", default_config)
         }
         Err(e) => panic!("couldn't create config file: {:?}", e),
     }
@@ -8,6 +8,8 @@ use crate::{cli::get_fe_dir, commands::Commands};
 pub struct Config {
     pub commands: Option<Commands>,
     pub data_dir: Option<String>,
+    // how often the indexed dirs are refreshed
+    pub index_refresh_minutes: Option<u64>,
 }
 
 /// Reads and parses the configuration file from the given path, returning a Config.
@@ -25,7 +27,7 @@ pub fn parse_config(file_path: &PathBuf) -> Result<Config, Box<dyn std::error::E
     if let Some(cmds) = &mut config.commands {
         let base_path = file_path.parent().unwrap_or(Path::new("/"));
 
-        for file_cmds in &mut cmds.file {
+        if let Some(file_cmds) = &mut cmds.file {
             for cmd in file_cmds {
                 let script_path = Path::new(&cmd.script);
                 if script_path.exists() && !script_path.is_absolute() {
@@ -34,7 +36,7 @@ pub fn parse_config(file_path: &PathBuf) -> Result<Config, Box<dyn std::error::E
                 }
             }
         }
-        for dir_cmds in &mut cmds.dir {
+        if let Some(dir_cmds) = &mut cmds.dir {
             for cmd in dir_cmds {
                 let script_path = Path::new(&cmd.script);
                 if script_path.exists() && !script_path.is_absolute() {
@@ -45,6 +47,6 @@ pub fn parse_config(file_path: &PathBuf) -> Result<Config, Box<dyn std::error::E
         }
     }
 
-    println!("config: {:?}", config);
+    eprintln!("config: {:?}", config);
     Ok(config)
 }
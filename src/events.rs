@@ -1,35 +1,111 @@
 use egui::{self, DroppedFile};
 
 use crate::commands::{DirCommand, FileCommand};
-use crate::utils;
+use crate::fe::jobs::{JobId, JobResult};
+use crate::fe::selection::{CursorMove, SelectMode};
 use crate::utils::dir::FeEntry;
+use crate::utils::trash::TrashItem;
+use crate::utils::watcher::DirChange;
 
 #[derive(Debug)]
 pub enum Modifier {
-    _Alt,
+    Alt,
     _Ctrl,
-    _Shift,
+    Shift,
     _MacCmd,
     Cmd,
 }
 
+impl Modifier {
+    fn to_egui(&self) -> egui::Modifiers {
+        return match self {
+            Modifier::Alt => egui::Modifiers::ALT,
+            Modifier::_Ctrl => egui::Modifiers::CTRL,
+            Modifier::Shift => egui::Modifiers::SHIFT,
+            Modifier::_MacCmd => egui::Modifiers::MAC_CMD,
+            Modifier::Cmd => egui::Modifiers::COMMAND,
+        };
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EventType {
     FocusPathBar,
     FocusSearchBar,
-    DirGoBack,
+    // go back or forward that many dirs in the history
+    DirGoBack(usize),
+    DirGoForward(usize),
     FavoriteCurrentPath,
     NewFile,
     OpenTerminal,
     SetPath(std::path::PathBuf),
-    DeleteFile(utils::dir::FeEntry),
+    // delete events take the entries to delete.
+    // Empty means the selection, or the entry under the mouse.
+    DeleteFile(Vec<FeEntry>),
+    DeletePermanently(Vec<FeEntry>),
     Exec(std::path::PathBuf),
     RunDirCmd(DirCommand),
-    RunFileCmd(FileCommand, std::path::PathBuf),
+    // runs the command once for each path
+    RunFileCmd(FileCommand, Vec<std::path::PathBuf>),
     MoveFile(u8, Vec<DroppedFile>),
-    StartDragEntry(FeEntry),
+    StartDragEntry(Vec<FeEntry>),
     EndDragEntry,
     ReloadDir,
+    // copy and cut take the entries to put in the clipboard.
+    // Empty means the selection, or the entry under the mouse.
+    Copy(Vec<FeEntry>),
+    Cut(Vec<FeEntry>),
+    Paste,
+    CancelJob(JobId),
+    JobFinished(JobResult),
+    OpenTrash,
+    RestoreTrashItem(TrashItem),
+    EmptyTrash(Vec<TrashItem>),
+    Undo,
+    Redo,
+    // starts renaming the entry, or batch renaming the entries if there
+    // are many. Empty means the selection, or the entry under the mouse.
+    StartRename(Vec<FeEntry>),
+    RenameEntry(FeEntry, String),
+    CancelRename,
+    // empty means the selection, or every entry in the current dir
+    BatchRename(Vec<FeEntry>),
+    SelectEntry(FeEntry, SelectMode),
+    SelectAll,
+    ClearSelection,
+    MoveCursor(CursorMove, SelectMode),
+    // opens the entry under the cursor
+    OpenCursor,
+    // text typed while no text input has focus
+    TypeAhead(String),
+    NewTab,
+    // None means the active tab
+    CloseTab(Option<usize>),
+    SelectTab(usize),
+    NextTab,
+    PrevTab,
+    ToggleSplit,
+    // gives focus to the other pane of the split view
+    SwitchPane,
+    // copy or move the selection into the dir of the other pane
+    CopyToOtherPane,
+    MoveToOtherPane,
+    GoToParent,
+    // opens the dialog to jump to a visited dir
+    OpenJump,
+    // opens the dir of the entry with the entry selected
+    RevealEntry(std::path::PathBuf),
+    // entries of the dirs of some tabs changed on disk
+    DirChanged(Vec<DirChange>),
+    // opens the dialog searching the file index
+    OpenIndexSearch,
+    // shows or hides the panel searching file contents
+    ToggleGrep,
+    // opens the file with the default application of the system
+    OpenDefault(std::path::PathBuf),
+    // creates a link to each entry next to it. Empty means the
+    // selection, or the entry under the mouse.
+    CreateSymlink(Vec<FeEntry>),
     _Quit,
 }
 
@@ -37,6 +113,8 @@ pub struct Event {
     event: EventType,
     modifiers: Vec<Modifier>,
     key: Vec<egui::Key>,
+    // global events are emitted even if a text input has focus
+    global: bool,
 }
 
 pub struct EventPool {
@@ -47,39 +125,235 @@ pub struct EventPool {
 
 impl EventPool {
     pub fn new() -> Self {
+        let mut commands = Vec::from([
+            Event {
+                event: EventType::FocusPathBar,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::L]),
+                global: true,
+            },
+            Event {
+                event: EventType::FocusSearchBar,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::F]),
+                global: true,
+            },
+            Event {
+                event: EventType::DirGoBack(1),
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::O]),
+                global: true,
+            },
+            Event {
+                event: EventType::DirGoBack(1),
+                modifiers: Vec::from([Modifier::Alt]),
+                key: Vec::from([egui::Key::ArrowLeft]),
+                global: true,
+            },
+            Event {
+                event: EventType::DirGoForward(1),
+                modifiers: Vec::from([Modifier::Alt]),
+                key: Vec::from([egui::Key::ArrowRight]),
+                global: true,
+            },
+            Event {
+                event: EventType::FavoriteCurrentPath,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::B]),
+                global: true,
+            },
+            Event {
+                event: EventType::NewFile,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::N]),
+                global: true,
+            },
+            Event {
+                event: EventType::ReloadDir,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::R]),
+                global: true,
+            },
+            Event {
+                event: EventType::Copy(Vec::new()),
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::C]),
+                global: false,
+            },
+            Event {
+                event: EventType::Cut(Vec::new()),
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::X]),
+                global: false,
+            },
+            Event {
+                event: EventType::Paste,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::V]),
+                global: false,
+            },
+            Event {
+                event: EventType::Undo,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::Z]),
+                global: false,
+            },
+            Event {
+                event: EventType::Redo,
+                modifiers: Vec::from([Modifier::Cmd, Modifier::Shift]),
+                key: Vec::from([egui::Key::Z]),
+                global: false,
+            },
+            Event {
+                event: EventType::StartRename(Vec::new()),
+                modifiers: Vec::new(),
+                key: Vec::from([egui::Key::F2]),
+                global: false,
+            },
+            Event {
+                event: EventType::OpenCursor,
+                modifiers: Vec::new(),
+                key: Vec::from([egui::Key::Enter]),
+                global: false,
+            },
+            Event {
+                event: EventType::GoToParent,
+                modifiers: Vec::new(),
+                key: Vec::from([egui::Key::Backspace]),
+                global: false,
+            },
+            Event {
+                event: EventType::SelectAll,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::A]),
+                global: false,
+            },
+            Event {
+                event: EventType::ClearSelection,
+                modifiers: Vec::new(),
+                key: Vec::from([egui::Key::Escape]),
+                global: false,
+            },
+            Event {
+                event: EventType::DeleteFile(Vec::new()),
+                modifiers: Vec::new(),
+                key: Vec::from([egui::Key::Delete]),
+                global: false,
+            },
+            Event {
+                event: EventType::DeletePermanently(Vec::new()),
+                modifiers: Vec::from([Modifier::Shift]),
+                key: Vec::from([egui::Key::Delete]),
+                global: false,
+            },
+            Event {
+                event: EventType::OpenJump,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::J]),
+                global: true,
+            },
+            Event {
+                event: EventType::OpenIndexSearch,
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([egui::Key::P]),
+                global: true,
+            },
+            Event {
+                event: EventType::ToggleGrep,
+                modifiers: Vec::from([Modifier::Cmd, Modifier::Shift]),
+                key: Vec::from([egui::Key::F]),
+                global: true,
+            },
+        ]);
+
+        commands.push(Event {
+            event: EventType::NewTab,
+            modifiers: Vec::from([Modifier::Cmd]),
+            key: Vec::from([egui::Key::T]),
+            global: true,
+        });
+        commands.push(Event {
+            event: EventType::CloseTab(None),
+            modifiers: Vec::from([Modifier::Cmd]),
+            key: Vec::from([egui::Key::W]),
+            global: true,
+        });
+        commands.push(Event {
+            event: EventType::NextTab,
+            modifiers: Vec::from([Modifier::Cmd]),
+            key: Vec::from([egui::Key::Tab]),
+            global: true,
+        });
+        commands.push(Event {
+            event: EventType::PrevTab,
+            modifiers: Vec::from([Modifier::Cmd, Modifier::Shift]),
+            key: Vec::from([egui::Key::Tab]),
+            global: true,
+        });
+        commands.push(Event {
+            event: EventType::SwitchPane,
+            modifiers: Vec::new(),
+            key: Vec::from([egui::Key::Tab]),
+            global: false,
+        });
+        commands.push(Event {
+            event: EventType::CopyToOtherPane,
+            modifiers: Vec::new(),
+            key: Vec::from([egui::Key::F5]),
+            global: false,
+        });
+        commands.push(Event {
+            event: EventType::MoveToOtherPane,
+            modifiers: Vec::new(),
+            key: Vec::from([egui::Key::F6]),
+            global: false,
+        });
+        let num_keys = [
+            egui::Key::Num1,
+            egui::Key::Num2,
+            egui::Key::Num3,
+            egui::Key::Num4,
+            egui::Key::Num5,
+            egui::Key::Num6,
+            egui::Key::Num7,
+            egui::Key::Num8,
+            egui::Key::Num9,
+        ];
+        for (i, key) in num_keys.into_iter().enumerate() {
+            commands.push(Event {
+                event: EventType::SelectTab(i),
+                modifiers: Vec::from([Modifier::Cmd]),
+                key: Vec::from([key]),
+                global: true,
+            });
+        }
+
+        // arrows and friends move the cursor, and extend the selection
+        // when holding Shift
+        for (key, movement) in [
+            (egui::Key::ArrowUp, CursorMove::Up),
+            (egui::Key::ArrowDown, CursorMove::Down),
+            (egui::Key::PageUp, CursorMove::PageUp),
+            (egui::Key::PageDown, CursorMove::PageDown),
+            (egui::Key::Home, CursorMove::Home),
+            (egui::Key::End, CursorMove::End),
+        ] {
+            commands.push(Event {
+                event: EventType::MoveCursor(movement, SelectMode::Replace),
+                modifiers: Vec::new(),
+                key: Vec::from([key]),
+                global: false,
+            });
+            commands.push(Event {
+                event: EventType::MoveCursor(movement, SelectMode::Range),
+                modifiers: Vec::from([Modifier::Shift]),
+                key: Vec::from([key]),
+                global: false,
+            });
+        }
+
         return Self {
-            commands: Vec::from([
-                Event {
-                    event: EventType::FocusPathBar,
-                    modifiers: Vec::from([Modifier::Cmd]),
-                    key: Vec::from([egui::Key::L]),
-                },
-                Event {
-                    event: EventType::FocusSearchBar,
-                    modifiers: Vec::from([Modifier::Cmd]),
-                    key: Vec::from([egui::Key::F]),
-                },
-                Event {
-                    event: EventType::DirGoBack,
-                    modifiers: Vec::from([Modifier::Cmd]),
-                    key: Vec::from([egui::Key::O]),
-                },
-                Event {
-                    event: EventType::FavoriteCurrentPath,
-                    modifiers: Vec::from([Modifier::Cmd]),
-                    key: Vec::from([egui::Key::B]),
-                },
-                Event {
-                    event: EventType::NewFile,
-                    modifiers: Vec::from([Modifier::Cmd]),
-                    key: Vec::from([egui::Key::N]),
-                },
-                Event {
-                    event: EventType::ReloadDir,
-                    modifiers: Vec::from([Modifier::Cmd]),
-                    key: Vec::from([egui::Key::R]),
-                },
-            ]),
+            commands,
             events: Vec::new(),
             next_frame_events: Vec::new(),
         };
@@ -96,34 +370,51 @@ impl EventPool {
     pub fn emit_input_events(&mut self, ctx: &egui::Context) {
         // flush last frame's events
         let mut events_to_emit = Vec::new();
+        let typing = ctx.wants_keyboard_input();
 
         'cmd_loop: for cmd in &self.commands {
+            if typing && !cmd.global {
+                continue;
+            }
+
             // check keys
             for key in &cmd.key {
-                if !ctx.input(|i| i.key_pressed(*key)) {
+                if !ctx.input(|i| key_pressed(i, *key)) {
                     continue 'cmd_loop;
                 }
             }
-            println!("found {:?}", cmd.key);
-
-            // check modifiers
-            for m in &cmd.modifiers {
-                if !ctx.input(|i| {
-                    return match &m {
-                        Modifier::_Alt => i.modifiers.alt,
-                        Modifier::_Ctrl => i.modifiers.ctrl,
-                        Modifier::_Shift => i.modifiers.shift,
-                        Modifier::_MacCmd => i.modifiers.mac_cmd,
-                        Modifier::Cmd => i.modifiers.command,
-                    };
-                }) {
-                    println!("didn't found {:?}", cmd.modifiers);
-                    continue 'cmd_loop;
-                }
+
+            // check modifiers. They must match exactly, so that e.g.
+            // Delete and Shift + Delete can be bound to different events.
+            let pattern = cmd
+                .modifiers
+                .iter()
+                .fold(egui::Modifiers::NONE, |pattern, m| pattern | m.to_egui());
+            if !ctx.input(|i| i.modifiers.matches_exact(pattern)) {
+                continue 'cmd_loop;
             }
             events_to_emit.push(cmd.event.clone());
         }
 
+        // mouse back and forward buttons
+        if ctx.input(|i| i.pointer.button_pressed(egui::PointerButton::Extra1)) {
+            events_to_emit.push(EventType::DirGoBack(1));
+        }
+        if ctx.input(|i| i.pointer.button_pressed(egui::PointerButton::Extra2)) {
+            events_to_emit.push(EventType::DirGoForward(1));
+        }
+
+        // text typed outside of text inputs jumps to matching entries
+        if !typing {
+            ctx.input(|i| {
+                for e in &i.events {
+                    if let egui::Event::Text(text) = e {
+                        events_to_emit.push(EventType::TypeAhead(text.clone()));
+                    }
+                }
+            });
+        }
+
         // emit collected events
         for event in events_to_emit {
             self.emit_event(event);
@@ -149,3 +440,22 @@ impl EventPool {
         self.next_frame_events.push(event);
     }
 }
+
+// egui doesn't emit key presses for Cmd + C/X/V (and Shift + Delete on
+// Windows), it emits clipboard events instead. Map them back to their
+// keys so they can be bound.
+fn key_pressed(input: &egui::InputState, key: egui::Key) -> bool {
+    if input.key_pressed(key) {
+        return true;
+    }
+
+    return input.events.iter().any(|e| {
+        matches!(
+            (e, key),
+            (egui::Event::Copy, egui::Key::C)
+                | (egui::Event::Cut, egui::Key::X)
+                | (egui::Event::Cut, egui::Key::Delete)
+                | (egui::Event::Paste(_), egui::Key::V)
+        )
+    });
+}
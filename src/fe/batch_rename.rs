@@ -0,0 +1,652 @@
+use egui_extras::{Column, TableBuilder};
+use regex::Regex;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+use super::jobs::JobKind;
+use super::FE;
+use crate::utils::dir::{validate_new_name, EntryKind, FeEntry};
+
+// the largest counter start and step the dialog accepts
+const MAX_COUNTER: usize = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseChange {
+    Keep,
+    Lower,
+    Upper,
+    Title,
+}
+
+impl CaseChange {
+    const ALL: [CaseChange; 4] = [
+        CaseChange::Keep,
+        CaseChange::Lower,
+        CaseChange::Upper,
+        CaseChange::Title,
+    ];
+
+    fn label(&self) -> &'static str {
+        return match self {
+            CaseChange::Keep => "Keep",
+            CaseChange::Lower => "lowercase",
+            CaseChange::Upper => "UPPERCASE",
+            CaseChange::Title => "Title Case",
+        };
+    }
+
+    fn apply(&self, s: &str) -> String {
+        return match self {
+            CaseChange::Keep => s.to_string(),
+            CaseChange::Lower => s.to_lowercase(),
+            CaseChange::Upper => s.to_uppercase(),
+            CaseChange::Title => {
+                let mut title = String::with_capacity(s.len());
+                let mut new_word = true;
+                for c in s.chars() {
+                    if new_word {
+                        title.extend(c.to_uppercase());
+                    } else {
+                        title.extend(c.to_lowercase());
+                    }
+                    new_word = c.is_whitespace() || c == '_' || c == '-' || c == '.';
+                }
+                title
+            }
+        };
+    }
+}
+
+// RenameRules are applied, in order, to the name of every entry being
+// renamed. The extension is kept apart and only changed by `new_ext`.
+#[derive(Debug, Clone)]
+pub struct RenameRules {
+    pub find: String,
+    pub replace: String,
+    pub use_regex: bool,
+    // the new name; `{name}` is the name after find and replace,
+    // `{n}` the counter
+    pub template: String,
+    pub counter_start: usize,
+    pub counter_step: usize,
+    pub counter_padding: usize,
+    pub case: CaseChange,
+    pub change_ext: bool,
+    pub new_ext: String,
+}
+
+impl Default for RenameRules {
+    fn default() -> Self {
+        Self {
+            find: String::new(),
+            replace: String::new(),
+            use_regex: false,
+            template: "{name}".to_string(),
+            counter_start: 1,
+            counter_step: 1,
+            counter_padding: 0,
+            case: CaseChange::Keep,
+            change_ext: false,
+            new_ext: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewRow {
+    pub entry: FeEntry,
+    pub new_name: String,
+    // why the entry can't be renamed
+    pub error: Option<String>,
+}
+
+impl PreviewRow {
+    pub fn is_changed(&self) -> bool {
+        return self.entry.name != OsString::from(&self.new_name);
+    }
+}
+
+// returns the new name of every entry, flagging the ones that can't be
+// renamed. `dir_entries` are all the entries in the dir, to check for
+// collisions. Fails if the rules themselves are invalid.
+pub fn preview(
+    rules: &RenameRules,
+    entries: &[FeEntry],
+    dir_entries: &[FeEntry],
+) -> Result<Vec<PreviewRow>, String> {
+    let regex = if rules.use_regex && !rules.find.is_empty() {
+        Some(Regex::new(&rules.find).map_err(|err| err.to_string())?)
+    } else {
+        None
+    };
+
+    let mut rows: Vec<PreviewRow> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let (new_name, error) = match entry.name.to_str() {
+                Some(name) => match new_name(rules, regex.as_ref(), entry, name, i) {
+                    Ok(new_name) => (new_name, None),
+                    Err(err) => (name.to_string(), Some(err)),
+                },
+                None => (
+                    entry.name.to_string_lossy().to_string(),
+                    Some("name isn't valid UTF-8".to_string()),
+                ),
+            };
+            PreviewRow {
+                entry: entry.clone(),
+                new_name,
+                error,
+            }
+        })
+        .collect();
+
+    // names that will be free or taken once the batch is applied
+    let mut new_name_count: HashMap<&str, usize> = HashMap::new();
+    for row in &rows {
+        *new_name_count.entry(&row.new_name).or_default() += 1;
+    }
+    let renamed_away: Vec<&OsString> = rows
+        .iter()
+        .filter(|row| row.is_changed())
+        .map(|row| &row.entry.name)
+        .collect();
+
+    let mut errors = Vec::new();
+    for row in &rows {
+        if row.error.is_some() || !row.is_changed() {
+            errors.push(row.error.clone());
+            continue;
+        }
+        let name = OsString::from(&row.new_name);
+        let error = if let Err(err) = validate_new_name(&name, &row.entry, &[]) {
+            Some(err)
+        } else if new_name_count[row.new_name.as_str()] > 1 {
+            Some("another entry gets the same name".to_string())
+        } else if dir_entries
+            .iter()
+            .any(|e| e.name == name && !renamed_away.contains(&&e.name))
+        {
+            Some(format!("{} already exists", row.new_name))
+        } else {
+            None
+        };
+        errors.push(error);
+    }
+    for (row, error) in rows.iter_mut().zip(errors) {
+        row.error = error;
+    }
+    return Ok(rows);
+}
+
+fn new_name(
+    rules: &RenameRules,
+    regex: Option<&Regex>,
+    entry: &FeEntry,
+    name: &str,
+    index: usize,
+) -> Result<String, String> {
+    let (stem, ext) = match entry.entry_type {
+        EntryKind::Dir(_) => (name, None),
+        EntryKind::File(_) => match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+            _ => (name, None),
+        },
+    };
+
+    let stem = match regex {
+        Some(regex) => regex.replace_all(stem, rules.replace.as_str()).to_string(),
+        None if !rules.find.is_empty() => stem.replace(&rules.find, &rules.replace),
+        None => stem.to_string(),
+    };
+
+    let counter = index
+        .checked_mul(rules.counter_step)
+        .and_then(|n| n.checked_add(rules.counter_start))
+        .ok_or_else(|| "the counter is too large".to_string())?;
+    let counter = format!("{:0width$}", counter, width = rules.counter_padding);
+    // the counter goes first, so names containing `{n}` aren't changed
+    let stem = rules
+        .template
+        .replace("{n}", &counter)
+        .replace("{name}", &stem);
+    let stem = rules.case.apply(&stem);
+
+    let ext = if rules.change_ext {
+        Some(rules.new_ext.trim_start_matches('.'))
+    } else {
+        ext
+    };
+    return match ext {
+        Some(ext) if !ext.is_empty() => Ok(format!("{}.{}", stem, ext)),
+        _ => Ok(stem),
+    };
+}
+
+// returns the indexes of the rows to rename in an order where no rename
+// overwrites an entry that is still waiting to be renamed, and the
+// indexes of the rows that can't be ordered.
+fn order(rows: &[PreviewRow]) -> (Vec<usize>, Vec<usize>) {
+    let mut pending: Vec<usize> = (0..rows.len())
+        .filter(|&i| rows[i].error.is_none() && rows[i].is_changed())
+        .collect();
+    let mut ordered = Vec::new();
+
+    loop {
+        let ready: Vec<usize> = pending
+            .iter()
+            .copied()
+            .filter(|&i| {
+                let target = OsString::from(&rows[i].new_name);
+                !pending
+                    .iter()
+                    .any(|&j| j != i && rows[j].entry.name == target)
+            })
+            .collect();
+        if ready.is_empty() {
+            return (ordered, pending);
+        }
+        pending.retain(|i| !ready.contains(i));
+        ordered.extend(ready);
+    }
+}
+
+// returns the (origin, destination) pairs renaming the rows, see `order`.
+// The renames that can't be ordered, like swaps, go through a temporary
+// name: they're moved away first and to their new name last.
+fn rename_pairs(rows: &[PreviewRow]) -> Vec<(PathBuf, PathBuf)> {
+    let (ordered, circular) = order(rows);
+    let target = |i: usize| rows[i].entry.path.with_file_name(&rows[i].new_name);
+
+    let mut pairs = Vec::new();
+    let mut temps = Vec::new();
+    for &i in &circular {
+        let path = &rows[i].entry.path;
+        let mut counter = 1;
+        let temp = loop {
+            let mut name = OsString::from(format!(".fe-rename-{}-", counter));
+            name.push(&rows[i].entry.name);
+            let temp = path.with_file_name(name);
+            if fs::symlink_metadata(&temp).is_err() && !temps.contains(&temp) {
+                break temp;
+            }
+            counter += 1;
+        };
+        pairs.push((path.clone(), temp.clone()));
+        temps.push(temp);
+    }
+    pairs.extend(
+        ordered
+            .into_iter()
+            .map(|i| (rows[i].entry.path.clone(), target(i))),
+    );
+    pairs.extend(
+        temps
+            .into_iter()
+            .zip(circular)
+            .map(|(temp, i)| (temp, target(i))),
+    );
+    return pairs;
+}
+
+// BatchRename is the state of the batch rename dialog
+pub struct BatchRename {
+    pub entries: Vec<FeEntry>,
+    pub rules: RenameRules,
+}
+
+impl FE {
+    pub fn start_batch_rename(&mut self, entries: Vec<FeEntry>) {
+        self.batch_rename = Some(BatchRename {
+            entries,
+            rules: RenameRules::default(),
+        });
+    }
+
+    // renames every entry as a single move job, so it's undone at once
+    fn apply_batch_rename(&mut self, rows: &[PreviewRow]) {
+        let pairs = rename_pairs(rows);
+        if !pairs.is_empty() {
+            self.jobs.spawn(JobKind::Move(pairs));
+        }
+        self.batch_rename = None;
+    }
+
+    pub fn draw_batch_rename(&mut self, ctx: &egui::Context) {
+        let state = match &mut self.batch_rename {
+            Some(state) => state,
+            None => return,
+        };
+
+        let mut open = true;
+        let mut apply = None;
+        let mut cancel = false;
+
+        egui::Window::new("Batch rename")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                let rules = &mut state.rules;
+                egui::Grid::new("batch_rename_rules")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Find");
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut rules.find);
+                            ui.checkbox(&mut rules.use_regex, "Regex");
+                        });
+                        ui.end_row();
+
+                        ui.label("Replace");
+                        ui.text_edit_singleline(&mut rules.replace);
+                        ui.end_row();
+
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut rules.template)
+                            .on_hover_text("{name} is the name, {n} the counter");
+                        ui.end_row();
+
+                        ui.label("Counter");
+                        ui.horizontal(|ui| {
+                            ui.label("start");
+                            ui.add(
+                                egui::DragValue::new(&mut rules.counter_start)
+                                    .clamp_range(0..=MAX_COUNTER),
+                            );
+                            ui.label("step");
+                            ui.add(
+                                egui::DragValue::new(&mut rules.counter_step)
+                                    .clamp_range(0..=MAX_COUNTER),
+                            );
+                            ui.label("padding");
+                            ui.add(
+                                egui::DragValue::new(&mut rules.counter_padding)
+                                    .clamp_range(0..=10),
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("Case");
+                        egui::ComboBox::from_id_source("batch_rename_case")
+                            .selected_text(rules.case.label())
+                            .show_ui(ui, |ui| {
+                                for case in CaseChange::ALL {
+                                    ui.selectable_value(&mut rules.case, case, case.label());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Extension");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut rules.change_ext, "Change to");
+                            ui.add_enabled(
+                                rules.change_ext,
+                                egui::TextEdit::singleline(&mut rules.new_ext),
+                            );
+                        });
+                        ui.end_row();
+                    });
+
+                ui.separator();
+
+                let rows = match preview(
+                    &state.rules,
+                    &state.entries,
+                    &self.tabs[self.active_tab].entries,
+                ) {
+                    Ok(rows) => rows,
+                    Err(err) => {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                        Vec::new()
+                    }
+                };
+
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .resizable(false)
+                    .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+                    .column(Column::auto())
+                    .column(Column::auto())
+                    .column(Column::remainder())
+                    .max_scroll_height(300.0)
+                    .header(20.0, |mut header| {
+                        header.col(|ui| {
+                            ui.strong("Before");
+                        });
+                        header.col(|ui| {
+                            ui.strong("After");
+                        });
+                        header.col(|ui| {
+                            ui.strong("");
+                        });
+                    })
+                    .body(|mut body| {
+                        for row in &rows {
+                            body.row(self.style.row_height, |mut table_row| {
+                                table_row.col(|ui| {
+                                    ui.label(row.entry.name.to_string_lossy());
+                                });
+                                table_row.col(|ui| {
+                                    if row.is_changed() {
+                                        ui.strong(&row.new_name);
+                                    } else {
+                                        ui.weak(&row.new_name);
+                                    }
+                                });
+                                table_row.col(|ui| {
+                                    if let Some(err) = &row.error {
+                                        ui.colored_label(ui.visuals().error_fg_color, err);
+                                    }
+                                });
+                            });
+                        }
+                    });
+
+                ui.separator();
+                let changed = rows.iter().filter(|row| row.is_changed()).count();
+                let valid = rows.iter().all(|row| row.error.is_none());
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            valid && changed > 0,
+                            egui::Button::new(format!("Rename {} entries", changed)),
+                        )
+                        .clicked()
+                    {
+                        apply = Some(rows.clone());
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if let Some(rows) = apply {
+            self.apply_batch_rename(&rows);
+        } else if cancel || !open {
+            self.batch_rename = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::dir::{path_to_fe_entry, Dir, File};
+    use std::time::SystemTime;
+    use tempfile::tempdir;
+
+    fn file(name: &str) -> FeEntry {
+        let path = PathBuf::from("/tmp").join(name);
+        FeEntry {
+            name: name.into(),
+            path: path.clone(),
+            entry_type: EntryKind::File(File {
+                path,
+                is_exe: false,
+                size: 0,
+                modified: SystemTime::UNIX_EPOCH,
+                link: None,
+            }),
+        }
+    }
+
+    fn dir(name: &str) -> FeEntry {
+        FeEntry {
+            name: name.into(),
+            path: PathBuf::from("/tmp").join(name),
+            entry_type: EntryKind::Dir(Dir { link: None }),
+        }
+    }
+
+    fn new_names(rows: &[PreviewRow]) -> Vec<&str> {
+        rows.iter().map(|row| row.new_name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_preview_rules() {
+        let entries = vec![file("IMG_001.JPG"), file("IMG_002.JPG"), dir("my.photos")];
+
+        let rules = RenameRules {
+            find: r"IMG_(\d+)".to_string(),
+            replace: "photo-$1".to_string(),
+            use_regex: true,
+            case: CaseChange::Lower,
+            ..Default::default()
+        };
+        let rows = preview(&rules, &entries, &entries).unwrap();
+        assert_eq!(
+            new_names(&rows),
+            vec!["photo-001.JPG", "photo-002.JPG", "my.photos"]
+        );
+
+        let rules = RenameRules {
+            template: "{name} {n}".to_string(),
+            counter_start: 9,
+            counter_padding: 3,
+            case: CaseChange::Title,
+            change_ext: true,
+            new_ext: ".jpeg".to_string(),
+            ..Default::default()
+        };
+        let rows = preview(&rules, &entries[..2], &entries).unwrap();
+        assert_eq!(
+            new_names(&rows),
+            vec!["Img_001 009.jpeg", "Img_002 010.jpeg"]
+        );
+
+        let rules = RenameRules {
+            find: "(".to_string(),
+            use_regex: true,
+            ..Default::default()
+        };
+        assert!(preview(&rules, &entries, &entries).is_err());
+
+        // a counter that overflows is an error of its row
+        let rules = RenameRules {
+            template: "{n}".to_string(),
+            counter_start: usize::MAX,
+            ..Default::default()
+        };
+        let rows = preview(&rules, &entries[..2], &entries).unwrap();
+        assert!(rows[0].error.is_none());
+        assert_eq!(rows[1].error.as_deref(), Some("the counter is too large"));
+    }
+
+    #[test]
+    fn test_preview_collisions() {
+        let entries = vec![file("a.txt"), file("b.txt"), file("c.txt")];
+
+        // both end up as x.txt
+        let rules = RenameRules {
+            template: "x".to_string(),
+            ..Default::default()
+        };
+        let rows = preview(&rules, &entries[..2], &entries).unwrap();
+        assert!(rows.iter().all(|row| row.error.is_some()));
+
+        // c.txt exists and isn't being renamed
+        let rules = RenameRules {
+            find: "a".to_string(),
+            replace: "c".to_string(),
+            ..Default::default()
+        };
+        let rows = preview(&rules, &entries[..1], &entries).unwrap();
+        assert!(rows[0].error.is_some());
+
+        // a -> b -> c works when b is renamed first
+        let rows = preview(
+            &RenameRules {
+                find: "b".to_string(),
+                replace: "c".to_string(),
+                ..Default::default()
+            },
+            &entries[1..2],
+            &entries[..2],
+        )
+        .unwrap();
+        assert!(rows[0].error.is_none());
+
+        let chain = vec![
+            PreviewRow {
+                entry: file("a.txt"),
+                new_name: "b.txt".to_string(),
+                error: None,
+            },
+            PreviewRow {
+                entry: file("b.txt"),
+                new_name: "c.txt".to_string(),
+                error: None,
+            },
+        ];
+        assert_eq!(order(&chain), (vec![1, 0], vec![]));
+
+        // swapping names can't be ordered, but is allowed
+        let rules = RenameRules {
+            template: "{n}".to_string(),
+            counter_start: 1,
+            ..Default::default()
+        };
+        let swap = vec![file("2.txt"), file("1.txt")];
+        let rows = preview(&rules, &swap, &swap).unwrap();
+        assert!(rows.iter().all(|row| row.error.is_none()));
+        assert_eq!(order(&rows), (vec![], vec![0, 1]));
+    }
+
+    #[test]
+    fn test_rename_cycle() {
+        let dir = tempdir().unwrap();
+        for name in ["a", "b", "c"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        let entries: Vec<FeEntry> = ["a", "b", "c"]
+            .iter()
+            .map(|name| path_to_fe_entry(&dir.path().join(name)).unwrap())
+            .collect();
+
+        // a -> b -> c -> a, with no rename overwriting an entry
+        let rows: Vec<PreviewRow> = entries
+            .into_iter()
+            .zip(["b", "c", "a"])
+            .map(|(entry, new_name)| PreviewRow {
+                entry,
+                new_name: new_name.to_string(),
+                error: None,
+            })
+            .collect();
+        let pairs = rename_pairs(&rows);
+        assert_eq!(pairs.len(), 6);
+        for (from, to) in pairs {
+            assert!(!to.exists());
+            fs::rename(from, to).unwrap();
+        }
+        for (name, content) in [("a", "c"), ("b", "a"), ("c", "b")] {
+            assert_eq!(fs::read_to_string(dir.path().join(name)).unwrap(), content);
+        }
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+}
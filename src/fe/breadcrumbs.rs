@@ -0,0 +1,136 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::FE;
+use crate::events::EventType;
+
+// the dirs listed by an open separator dropdown, or why they can't be read
+type CachedSubdirs = Arc<Result<Vec<PathBuf>, String>>;
+
+// returns the dirs leading to `path`, from the root down to `path`
+pub fn ancestors(path: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = path.ancestors().map(PathBuf::from).collect();
+    dirs.reverse();
+    return dirs;
+}
+
+// returns the name shown for the dir in the breadcrumbs
+pub fn crumb_label(path: &Path) -> String {
+    return match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        // the root, or a windows drive
+        None => path.to_string_lossy().to_string(),
+    };
+}
+
+// lists the dirs inside `path`, sorted by name
+pub fn subdirs(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    dirs.sort_by_key(|dir| dir.file_name().map(|name| name.to_ascii_lowercase()));
+    return Ok(dirs);
+}
+
+impl FE {
+    // draws the active tab's path as a button per dir. The separators list
+    // the dirs next to the following one, and clicking the empty space
+    // after the path switches to the path input.
+    pub fn draw_breadcrumbs(&mut self, ui: &mut egui::Ui) {
+        let start = ui.cursor().min.x;
+        let dirs = ancestors(&self.tabs[self.active_tab].path);
+        for (i, dir) in dirs.iter().enumerate() {
+            if i > 0 {
+                self.draw_crumb_separator(ui, &dirs[i - 1]);
+            }
+            if ui
+                .button(crumb_label(dir))
+                .on_hover_text(dir.to_string_lossy())
+                .clicked()
+            {
+                self.event_pool.emit_event(EventType::SetPath(dir.clone()));
+            }
+        }
+        if let Some(dir) = dirs.last() {
+            self.draw_crumb_separator(ui, dir);
+        }
+
+        // the path input is about as wide
+        let used = ui.cursor().min.x - start;
+        let width = (ui.spacing().text_edit_width - used).max(ui.spacing().interact_size.x);
+        let space = ui
+            .allocate_response(
+                egui::vec2(width, ui.spacing().interact_size.y),
+                egui::Sense::click(),
+            )
+            .on_hover_cursor(egui::CursorIcon::Text);
+        if space.clicked() {
+            self.event_pool.emit_event(EventType::FocusPathBar);
+        }
+    }
+
+    // draws a dropdown listing the dirs inside `parent`
+    fn draw_crumb_separator(&mut self, ui: &mut egui::Ui, parent: &Path) {
+        // the dirs are read once when the dropdown opens, and kept in the
+        // ui memory while it's open
+        let id = egui::Id::new("crumb_subdirs").with(parent);
+        let menu = ui.menu_button("⏵", |ui| {
+            let dirs = ui.data_mut(|data| {
+                data.get_temp_mut_or_insert_with::<CachedSubdirs>(id, || {
+                    Arc::new(subdirs(parent).map_err(|err| err.to_string()))
+                })
+                .clone()
+            });
+            match dirs.as_ref() {
+                Ok(dirs) if dirs.is_empty() => {
+                    ui.label("No dirs");
+                }
+                Ok(dirs) => {
+                    egui::ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            for dir in dirs {
+                                if ui.button(crumb_label(dir)).clicked() {
+                                    self.event_pool.emit_event(EventType::SetPath(dir.clone()));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                }
+                Err(err) => {
+                    ui.label(err);
+                }
+            }
+        });
+        if menu.inner.is_none() {
+            ui.data_mut(|data| data.remove::<CachedSubdirs>(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_breadcrumbs() {
+        let dirs = ancestors(Path::new("/home/user/docs"));
+        let labels: Vec<_> = dirs.iter().map(|dir| crumb_label(dir)).collect();
+        assert_eq!(labels, vec!["/", "home", "user", "docs"]);
+        assert_eq!(dirs[2], PathBuf::from("/home/user"));
+
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::create_dir(dir.path().join("A")).unwrap();
+        fs::write(dir.path().join("c.txt"), "c").unwrap();
+        let subdirs = subdirs(dir.path()).unwrap();
+        assert_eq!(subdirs, vec![dir.path().join("A"), dir.path().join("b")]);
+    }
+}
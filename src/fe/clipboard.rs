@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use crate::utils::dir::FeEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardMode {
+    Copy,
+    Cut,
+}
+
+// Clipboard holds the entries copied or cut inside fe,
+// waiting to be pasted into another dir.
+#[derive(Debug, Clone)]
+pub struct Clipboard {
+    pub entries: Vec<FeEntry>,
+    pub mode: ClipboardMode,
+}
+
+impl Clipboard {
+    pub fn new(entries: Vec<FeEntry>, mode: ClipboardMode) -> Self {
+        let mut unique: Vec<FeEntry> = Vec::new();
+        for entry in entries {
+            if !unique.iter().any(|e| e.path == entry.path) {
+                unique.push(entry);
+            }
+        }
+        Self {
+            entries: unique,
+            mode,
+        }
+    }
+
+    // publishes the entries to the system clipboard as a text/uri-list
+    // (RFC 2483), which is what other file managers expect when pasting
+    // files. `system` is opened on first use and must be kept: on X11 fe
+    // serves the clipboard contents itself.
+    pub fn publish(&self, system: &mut Option<arboard::Clipboard>) -> Result<(), arboard::Error> {
+        if system.is_none() {
+            *system = Some(arboard::Clipboard::new()?);
+        }
+        let paths: Vec<&PathBuf> = self.entries.iter().map(|e| &e.path).collect();
+        return system.as_mut().unwrap().set().file_list(&paths);
+    }
+}
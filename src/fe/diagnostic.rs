@@ -1,9 +1,14 @@
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use super::jobs::JobProgress;
+
 pub struct Diagnostic {
     pub message: String,
     pub expires_at: Instant,
-    _start_time: Instant,
+    start_time: Instant,
+    // when set, progress is read from the job instead of the elapsed time
+    job: Option<Arc<JobProgress>>,
 }
 
 impl Diagnostic {
@@ -13,7 +18,8 @@ impl Diagnostic {
         Self {
             message,
             expires_at: Instant::now() + duration,
-            _start_time: Instant::now(),
+            start_time: Instant::now(),
+            job: None,
         }
     }
 
@@ -25,9 +31,20 @@ impl Diagnostic {
         Self::default(format!("error: {:?}", err))
     }
 
-    pub fn _progress(&self) -> f32 {
-        let elapsed = self._start_time.elapsed().as_secs_f32();
-        let total_duration = (self.expires_at - self._start_time).as_secs_f32();
+    // creates a diagnostic that follows a job's progress
+    pub fn from_job(message: String, job: Arc<JobProgress>) -> Self {
+        Self {
+            job: Some(job),
+            ..Self::default(message)
+        }
+    }
+
+    pub fn progress(&self) -> f32 {
+        if let Some(job) = &self.job {
+            return job.fraction();
+        }
+        let elapsed = self.start_time.elapsed().as_secs_f32();
+        let total_duration = (self.expires_at - self.start_time).as_secs_f32();
         (elapsed / total_duration).min(1.0)
     }
 }
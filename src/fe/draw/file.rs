@@ -1,49 +1,84 @@
-use std::path::PathBuf;
-
 use crate::commands::Commands;
 use crate::events::{EventPool, EventType};
+use crate::fe::rename::RenameState;
+use crate::fe::selection::SelectMode;
 use crate::fe::style;
 use crate::utils;
-use crate::utils::dir::{get_parent, FeEntry};
-use egui::{Response, RichText, Ui};
-use egui_extras::TableBody;
+use crate::utils::dir::FeEntry;
+use egui::text::LayoutJob;
+use egui::{Response, RichText, TextFormat, Ui, WidgetText};
+use egui_extras::TableRow;
+
+// RowState is how a row is shown and what its actions apply to
+pub struct RowState {
+    // the entries the row's actions apply to: the selection if the row
+    // is part of it, otherwise the row's entry.
+    pub targets: Vec<FeEntry>,
+    pub selected: bool,
+    // the chars of the name matching the search or the type-ahead prefix
+    pub highlight: Vec<usize>,
+}
+
+impl RowState {
+    // returns the state of a row that isn't selected nor highlighted
+    pub fn new(entry: &FeEntry) -> Self {
+        Self {
+            targets: vec![entry.clone()],
+            selected: false,
+            highlight: Vec::new(),
+        }
+    }
+}
 
+// draws the ".." row pointing to `parent`
 pub fn draw_back_dir_row(
-    body: &mut TableBody,
-    current_path: PathBuf,
+    row: &mut TableRow,
+    parent: &FeEntry,
     style: &style::Style,
     event_pool: &mut EventPool,
     commands: &Commands,
 ) -> bool {
-    if let Some(entry) = get_parent(current_path) {
-        return draw_file_row(body, &entry, style, event_pool, commands);
-    }
-    return false;
+    let state = RowState::new(parent);
+    return draw_file_row(row, parent, &state, style, event_pool, commands, &mut None);
 }
 
 pub fn draw_file_row(
-    body: &mut TableBody,
+    row: &mut TableRow,
     entry: &FeEntry,
+    state: &RowState,
     style: &style::Style,
     event_pool: &mut EventPool,
     commands: &Commands,
+    renaming: &mut Option<RenameState>,
 ) -> bool {
     let mut responses = Vec::new();
     let mut hovered = false;
+    let targets = &state.targets;
 
-    body.row(style.row_height, |mut row| {
-        responses.push(row.col(|ui| draw_file_name_cell(ui, entry, style, event_pool, commands)));
-        responses.push(row.col(|ui| draw_file_size_cell(ui, entry, event_pool, commands)));
-        responses.push(row.col(|ui| draw_last_modified_cell(ui, entry, event_pool, commands)));
-        if row.response().hovered() {
-            hovered = true;
-        }
-    });
+    row.set_selected(state.selected);
+    responses.push(
+        row.col(|ui| draw_file_name_cell(ui, entry, state, style, event_pool, commands, renaming)),
+    );
+    responses.push(row.col(|ui| draw_file_size_cell(ui, entry, targets, event_pool, commands)));
+    responses.push(row.col(|ui| draw_last_modified_cell(ui, entry, targets, event_pool, commands)));
+    let response = row.response();
+    if response.hovered() {
+        hovered = true;
+    }
+    if response.clicked() {
+        let mode = SelectMode::from_modifiers(response.ctx.input(|i| i.modifiers));
+        event_pool.emit_event(EventType::SelectEntry(entry.clone(), mode));
+    }
+    // right clicking outside the selection selects the clicked entry,
+    // so the context menu acts on it.
+    if response.secondary_clicked() && !state.selected {
+        event_pool.emit_event(EventType::SelectEntry(entry.clone(), SelectMode::Replace));
+    }
 
     // if at this point the row isn't hovered, check if any cell is hovered
     if !hovered {
         let hover_pos = responses
-            .get(0)
+            .first()
             .and_then(|(_, response)| response.ctx.input(|i| i.pointer.hover_pos()));
 
         hovered = if let Some(hover_pos) = hover_pos {
@@ -54,9 +89,9 @@ pub fn draw_file_row(
     }
 
     for (_, response) in &responses {
-        response.context_menu(|ui| get_file_context_menu(ui, entry, event_pool, commands));
+        response.context_menu(|ui| get_file_context_menu(ui, targets, event_pool, commands));
         if response.drag_started() {
-            event_pool.emit_event(EventType::StartDragEntry(entry.clone()));
+            event_pool.emit_event(EventType::StartDragEntry(targets.clone()));
         }
     }
 
@@ -66,26 +101,45 @@ pub fn draw_file_row(
 pub fn draw_file_name_cell(
     ui: &mut egui::Ui,
     entry: &FeEntry,
+    state: &RowState,
     style: &style::Style,
     event_pool: &mut EventPool,
     commands: &Commands,
+    renaming: &mut Option<RenameState>,
 ) {
-    let name = entry.name.to_owned().to_str().unwrap().to_owned();
+    let name = entry.name.to_string_lossy();
     let icon = entry.get_icon();
+    let targets = &state.targets;
+    // links show where they point to
+    let tooltip = entry.entry_type.link().map(|link| link.description());
+    let with_tooltip = |response: Response| match &tooltip {
+        Some(tooltip) => response.on_hover_text(tooltip),
+        None => response,
+    };
 
     cell(ui, |ui| {
-        ui.label(icon);
+        with_tooltip(ui.label(icon));
+        if let Some(state) = renaming.as_mut().filter(|s| s.entry.path == entry.path) {
+            draw_rename_input(ui, state, event_pool);
+            return;
+        }
         match &entry.entry_type {
             utils::dir::EntryKind::Dir(_) => {
-                let link = ui.link(name);
-                link.context_menu(|ui| get_file_context_menu(ui, entry, event_pool, commands));
+                let link = ui.link(highlighted_name(ui, &name, &state.highlight, None));
+                let link = with_tooltip(link);
+                link.context_menu(|ui| get_file_context_menu(ui, targets, event_pool, commands));
                 if link.clicked() {
                     event_pool.emit_event(EventType::SetPath(entry.path.clone()));
                 }
             }
             utils::dir::EntryKind::File(file) => {
                 let resp = if let Some(e) = file.is_clickable(&commands.file) {
-                    let link = ui.link(RichText::new(name).color(style.colors.exe));
+                    let link = ui.link(highlighted_name(
+                        ui,
+                        &name,
+                        &state.highlight,
+                        Some(style.colors.exe),
+                    ));
                     if link.clicked() {
                         if file.is_exe {
                             event_pool.emit_event(EventType::Exec(entry.path.clone()));
@@ -94,19 +148,97 @@ pub fn draw_file_name_cell(
                     }
                     link
                 } else {
-                    ui.label(name)
+                    ui.label(highlighted_name(ui, &name, &state.highlight, None))
                 };
+                let resp = with_tooltip(resp);
                 resp.context_menu(|ui| {
-                    get_file_context_menu(ui, entry, event_pool, commands);
+                    get_file_context_menu(ui, targets, event_pool, commands);
                 });
             }
         }
     });
 }
 
+// returns the name with the chars at the `highlight` indexes highlighted
+pub fn highlighted_name(
+    ui: &Ui,
+    name: &str,
+    highlight: &[usize],
+    color: Option<egui::Color32>,
+) -> WidgetText {
+    let color = color.unwrap_or(egui::Color32::PLACEHOLDER);
+    if highlight.is_empty() {
+        return RichText::new(name).color(color).into();
+    }
+
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let mut job = LayoutJob::default();
+    let mut append = |text: &str, highlighted: bool| {
+        let background = match highlighted {
+            true => ui.visuals().selection.bg_fill,
+            false => egui::Color32::TRANSPARENT,
+        };
+        job.append(
+            text,
+            0.0,
+            TextFormat {
+                font_id: font_id.clone(),
+                color,
+                background,
+                ..Default::default()
+            },
+        );
+    };
+    // append runs of highlighted and plain chars
+    let mut run = String::new();
+    let mut run_highlighted = false;
+    for (i, c) in name.chars().enumerate() {
+        let highlighted = highlight.contains(&i);
+        if highlighted != run_highlighted && !run.is_empty() {
+            append(&run, run_highlighted);
+            run.clear();
+        }
+        run_highlighted = highlighted;
+        run.push(c);
+    }
+    append(&run, run_highlighted);
+    return job.into();
+}
+
+// draws the text input used to rename an entry. Enter or clicking
+// away commits the new name, Escape cancels.
+pub fn draw_rename_input(ui: &mut egui::Ui, state: &mut RenameState, event_pool: &mut EventPool) {
+    let input = ui.text_edit_singleline(&mut state.name);
+
+    if state.select_stem {
+        state.select_stem = false;
+        input.request_focus();
+        if let Some(mut text_state) = egui::TextEdit::load_state(ui.ctx(), input.id) {
+            let range = egui::text::CCursorRange::two(
+                egui::text::CCursor::new(0),
+                egui::text::CCursor::new(state.stem_len()),
+            );
+            text_state.cursor.set_char_range(Some(range));
+            text_state.store(ui.ctx(), input.id);
+        }
+    }
+
+    if input.lost_focus() {
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            event_pool.emit_event(EventType::CancelRename);
+        } else {
+            event_pool.emit_event(EventType::RenameEntry(
+                state.entry.clone(),
+                state.name.clone(),
+            ));
+        }
+    }
+}
+
 pub fn draw_file_size_cell(
     ui: &mut egui::Ui,
     entry: &FeEntry,
+    targets: &[FeEntry],
     event_pool: &mut EventPool,
     commands: &Commands,
 ) {
@@ -117,7 +249,7 @@ pub fn draw_file_size_cell(
         utils::dir::EntryKind::File(file) => {
             ui.label(utils::human_readable_size(file.size).to_string())
                 .context_menu(|ui| {
-                    get_file_context_menu(ui, entry, event_pool, commands);
+                    get_file_context_menu(ui, targets, event_pool, commands);
                 });
         }
     });
@@ -126,6 +258,7 @@ pub fn draw_file_size_cell(
 pub fn draw_last_modified_cell(
     ui: &mut egui::Ui,
     entry: &FeEntry,
+    targets: &[FeEntry],
     event_pool: &mut EventPool,
     commands: &Commands,
 ) {
@@ -136,69 +269,99 @@ pub fn draw_last_modified_cell(
         utils::dir::EntryKind::File(file) => {
             ui.label(utils::system_time_to_human_readable(file.modified))
                 .context_menu(|ui| {
-                    get_file_context_menu(ui, entry, event_pool, commands);
+                    get_file_context_menu(ui, targets, event_pool, commands);
                 });
         }
     });
 }
 
+// context menu for the targeted entries: the right-clicked entry, or the
+// selection if it was part of it.
 pub fn get_file_context_menu(
     ui: &mut Ui,
-    entry: &FeEntry,
+    targets: &[FeEntry],
     event_pool: &mut EventPool,
     commands: &Commands,
 ) {
     let mut close = false;
 
-    match &entry.entry_type {
-        utils::dir::EntryKind::Dir(_) => {
-            if ui.button("Open").clicked() {
-                close = true;
-                ui.close_menu();
-                event_pool.emit_event(EventType::SetPath(entry.path.clone()));
+    // actions that only make sense for one entry
+    if let [entry] = targets {
+        match &entry.entry_type {
+            utils::dir::EntryKind::Dir(_) => {
+                if ui.button("Open").clicked() {
+                    close = true;
+                    ui.close_menu();
+                    event_pool.emit_event(EventType::SetPath(entry.path.clone()));
+                }
             }
-        }
-        utils::dir::EntryKind::File(file) => {
-            if file.is_exe {
-                if ui.button("Run").clicked() {
+            utils::dir::EntryKind::File(file) => {
+                if file.is_exe && ui.button("Run").clicked() {
                     close = true;
                     ui.close_menu();
                     event_pool.emit_event(EventType::Exec(entry.path.clone()));
                 }
             }
         }
+
+        if ui.button("Properties").clicked() {
+            close = true;
+            ui.close_menu();
+            // TODO
+        }
     }
 
-    if ui.button("Properties").clicked() {
+    if ui.button("Copy path").clicked() {
         close = true;
+        let paths: Vec<String> = targets
+            .iter()
+            .map(|e| e.path.to_string_lossy().to_string())
+            .collect();
+        ui.ctx().output_mut(|o| o.copied_text = paths.join("\n"));
         ui.close_menu();
-        // TODO
     }
-    if ui.button("Copy path").clicked() {
+    if ui.button("Copy").clicked() {
         close = true;
-        ui.ctx()
-            .output_mut(|o| o.copied_text = entry.path.to_string_lossy().to_string());
-        ui.close_menu();
+        event_pool.emit_event(EventType::Copy(targets.to_vec()));
+    }
+    if ui.button("Cut").clicked() {
+        close = true;
+        event_pool.emit_event(EventType::Cut(targets.to_vec()));
+    }
+    if ui.button("Create symlink").clicked() {
+        close = true;
+        event_pool.emit_event(EventType::CreateSymlink(targets.to_vec()));
+    }
+    if ui.button("Move to Trash").clicked() {
+        close = true;
+        event_pool.emit_event(EventType::DeleteFile(targets.to_vec()));
     }
-    if ui.button("Delete").clicked() {
+    if ui.button("Delete permanently").clicked() {
         close = true;
-        event_pool.emit_event(EventType::DeleteFile(entry.clone()));
+        event_pool.emit_event(EventType::DeletePermanently(targets.to_vec()));
     }
-    if ui.button("Rename").clicked() {
+    let rename_label = if targets.len() > 1 {
+        "Batch rename..."
+    } else {
+        "Rename"
+    };
+    if ui.button(rename_label).clicked() {
         close = true;
-        println!("TODO rename file");
+        event_pool.emit_event(EventType::StartRename(targets.to_vec()));
     }
 
-    //custom file commands
-    if let utils::dir::EntryKind::File(file) = &entry.entry_type {
-        if let Some(file_commands) = commands.file.as_ref() {
-            for cmd in file_commands {
-                if !cmd.extensions.is_none() && file.is_of_ext(cmd.extensions.clone().unwrap()) {
-                    if ui.button(cmd.name.clone()).clicked() {
-                        event_pool
-                            .emit_event(EventType::RunFileCmd(cmd.clone(), entry.path.clone()))
-                    }
-                }
+    //custom file commands, shown if every target is a file they apply to
+    if let Some(file_commands) = commands.file.as_ref() {
+        for cmd in file_commands {
+            let applies = cmd.extensions.as_ref().is_some_and(|extensions| {
+                targets.iter().all(|entry| match &entry.entry_type {
+                    utils::dir::EntryKind::File(file) => file.is_of_ext(extensions.clone()),
+                    utils::dir::EntryKind::Dir(_) => false,
+                })
+            });
+            if applies && ui.button(cmd.name.clone()).clicked() {
+                let paths = targets.iter().map(|e| e.path.clone()).collect();
+                event_pool.emit_event(EventType::RunFileCmd(cmd.clone(), paths))
             }
         }
     }
@@ -218,6 +381,14 @@ pub fn get_current_dir_context_menu(ui: &mut Ui, event_pool: &mut EventPool, com
         close = true;
         event_pool.emit_event(EventType::NewFile)
     }
+    if ui.button("Paste").clicked() {
+        close = true;
+        event_pool.emit_event(EventType::Paste)
+    }
+    if ui.button("Batch rename...").clicked() {
+        close = true;
+        event_pool.emit_event(EventType::BatchRename(Vec::new()))
+    }
     if ui.button("Open Terminal").clicked() {
         close = true;
         event_pool.emit_event(EventType::OpenTerminal)
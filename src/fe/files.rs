@@ -1,67 +1,90 @@
+use super::clipboard::ClipboardMode;
 use super::draw;
+use super::draw::file::RowState;
+use super::jobs::{create_symlink, JobKind};
+use super::journal::Operation;
+use super::type_ahead::{matched_len, TypeAhead};
 use super::FE;
+use crate::events::EventType;
 use crate::fe::diagnostic::Diagnostic;
-use crate::utils;
 use crate::utils::dir::get_parent;
 use crate::utils::dir::get_sort_icon;
-use crate::utils::dir::DirSortingType;
-use crate::utils::dir::{
-    fs_to_fe_entry, get_valid_new_file, DirSorting, FeEntry, QuickAccessEntry, SortOrder,
-};
+use crate::utils::dir::{get_valid_new_file, DirSorting, FeEntry, QuickAccessEntry, SortOrder};
+use crate::utils::dir::{DirSortingType, EntryList};
+use crate::utils::watcher::DirChange;
 use egui_extras::{Column, TableBuilder};
 use std::ffi::OsString;
 use std::fs::File;
 use std::{
     fs::{self},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
+// max height of the file table before it scrolls
+const TABLE_HEIGHT: f32 = 600.0;
+
 impl FE {
-    // updates `path`, `path_string` and `prev_path` with argument.
+    // updates the active tab's path and loads its entries. Paths that
+    // aren't dirs are reported instead.
     pub fn set_path(&mut self, path: PathBuf) {
-        self.prev_path = Some(self.path.clone());
-        self.path = path;
-        self.path_string = self.path.to_str().unwrap().to_owned();
-        self.search_txt = String::new();
+        if !path.is_dir() {
+            let message = match path.exists() {
+                true => format!("not a directory: {}", path.to_string_lossy()),
+                false => format!("no such directory: {}", path.to_string_lossy()),
+            };
+            self.diagnostics.push(Diagnostic::default(message));
+            return;
+        }
+        self.tab_mut().set_path(path);
+        self.renaming = None;
         self.load_dir_entries();
+        self.record_visit();
     }
 
-    pub fn go_back_path(&mut self) {
-        match &self.prev_path {
-            Some(prev) => {
-                self.set_path(prev.clone());
-            }
-            None => {
-                println!("no previous dir to go back to")
-            }
+    // goes back `steps` dirs in the active tab's history, if there are any
+    pub fn go_back(&mut self, steps: usize) {
+        if self.tab_mut().go_back(steps) {
+            self.renaming = None;
+            self.load_dir_entries();
         }
     }
 
-    pub fn delete_entry(&mut self, entry: FeEntry) {
-        match entry.entry_type {
-            utils::dir::EntryKind::Dir(_) => {
-                if let Err(err) = fs::remove_dir_all(entry.path) {
-                    self.diagnostics.push(Diagnostic::from_err(&err));
-                }
-            }
-            utils::dir::EntryKind::File(_) => {
-                if let Err(err) = fs::remove_file(entry.path) {
-                    self.diagnostics.push(Diagnostic::from_err(&err));
-                }
-            }
+    // goes forward `steps` dirs in the active tab's history, if there are any
+    pub fn go_forward(&mut self, steps: usize) {
+        if self.tab_mut().go_forward(steps) {
+            self.renaming = None;
+            self.load_dir_entries();
         }
     }
 
+    // moves the entries to the trash in the background
+    pub fn trash_entries(&mut self, entries: Vec<FeEntry>) {
+        let paths = entries.into_iter().map(|e| e.path).collect();
+        self.jobs.spawn(JobKind::Trash(paths));
+    }
+
+    // asks for confirmation before permanently deleting the entries
+    pub fn confirm_delete(&mut self, entries: Vec<FeEntry>) {
+        let message = match entries.as_slice() {
+            [entry] => format!("Permanently delete {}?", entry.name.to_string_lossy()),
+            _ => format!("Permanently delete {} items?", entries.len()),
+        };
+        let paths = entries.into_iter().map(|e| e.path).collect();
+        self.confirm_job = Some((message, JobKind::Delete(paths)));
+    }
+
     // creates the file and resets the file creation state
     pub fn create_file(&mut self) {
-        let is_dir = self.new_file_name.chars().last() == Some('/');
-        let new_file_name = get_valid_new_file(&OsString::from(&self.new_file_name), &self.entries);
+        let is_dir = self.new_file_name.ends_with('/');
+        let new_file_name =
+            get_valid_new_file(&OsString::from(&self.new_file_name), &self.tab().entries);
 
-        let mut new_file_path = self.path.clone();
+        let mut new_file_path = self.tab().path.clone();
         new_file_path.push(new_file_name);
 
         let result = if is_dir {
-            fs::create_dir_all(new_file_path)
+            fs::create_dir_all(&new_file_path)
         } else {
             if let Some(parent) = new_file_path.parent() {
                 if let Err(err) = fs::create_dir_all(parent) {
@@ -71,8 +94,15 @@ impl FE {
             File::create(&new_file_path).map(|_| ())
         };
 
-        if let Err(err) = result {
-            self.diagnostics.push(Diagnostic::from_err(&err));
+        match result {
+            Ok(()) => {
+                self.journal.record(Operation::Create {
+                    path: new_file_path,
+                    is_dir,
+                });
+                self.save_journal();
+            }
+            Err(err) => self.diagnostics.push(Diagnostic::from_err(&err)),
         };
 
         self.creating_file = false;
@@ -80,45 +110,192 @@ impl FE {
         self.load_dir_entries();
     }
 
-    pub fn move_file(&mut self, origin: &PathBuf, dest: &PathBuf) {
-        if let Err(err) = fs::rename(origin, dest) {
-            self.diagnostics.push(Diagnostic::from_err(&err))
+    // creates a link to each entry next to it, named "Link to <name>".
+    // The link points to the name, so it survives moving their dir.
+    pub fn create_symlinks(&mut self, entries: Vec<FeEntry>) {
+        for entry in entries {
+            let dir = match entry.path.parent() {
+                Some(dir) => dir,
+                None => continue,
+            };
+            let mut name = OsString::from("Link to ");
+            name.push(&entry.name);
+            let mut path = dir.join(&name);
+            let mut counter = 1;
+            while fs::symlink_metadata(&path).is_ok() {
+                counter += 1;
+                let mut numbered = name.clone();
+                numbered.push(format!("-{}", counter));
+                path = dir.join(numbered);
+            }
+
+            let target = PathBuf::from(&entry.name);
+            match create_symlink(&target, &path) {
+                Ok(()) => self.journal.record(Operation::Link { path, target }),
+                Err(err) => self.diagnostics.push(Diagnostic::from_err(&err)),
+            }
+        }
+        self.save_journal();
+        self.load_dir_entries();
+    }
+
+    // pastes the clipboard entries into the current dir. Cut entries are
+    // moved and the clipboard is cleared, copied ones can be pasted again.
+    pub fn paste(&mut self) {
+        let clipboard = match &self.clipboard {
+            Some(clipboard) => clipboard.clone(),
+            None => return,
+        };
+        let dir = self.tab().path.clone();
+        let dir_entries = self.tab().entries.clone();
+        if self.transfer(clipboard.entries, clipboard.mode, &dir, dir_entries)
+            && clipboard.mode == ClipboardMode::Cut
+        {
+            self.clipboard = None;
+        }
+    }
+
+    // copies or moves the entries into `dir` in the background. Entries
+    // that collide with `dir_entries` get a new name. Returns whether
+    // there was anything to transfer.
+    pub fn transfer(
+        &mut self,
+        entries: Vec<FeEntry>,
+        mode: ClipboardMode,
+        dir: &Path,
+        dir_entries: Vec<FeEntry>,
+    ) -> bool {
+        // entries transferred so far also count for name collisions
+        let mut dest_entries = dir_entries;
+        let mut pairs = Vec::new();
+        for entry in entries {
+            if mode == ClipboardMode::Cut && entry.path.parent() == Some(dir) {
+                continue;
+            }
+
+            let name = get_valid_new_file(&entry.name, &dest_entries);
+            let dest = dir.join(&name);
+            if dest.starts_with(&entry.path) {
+                self.diagnostics.push(Diagnostic::default(format!(
+                    "can't paste {} into itself",
+                    entry.name.to_string_lossy()
+                )));
+                continue;
+            }
+
+            pairs.push((entry.path.clone(), dest.clone()));
+            dest_entries.push(FeEntry {
+                name,
+                path: dest,
+                entry_type: entry.entry_type,
+            });
         }
+
+        if pairs.is_empty() {
+            return false;
+        }
+        match mode {
+            ClipboardMode::Copy => self.jobs.spawn(JobKind::Copy(pairs)),
+            ClipboardMode::Cut => self.jobs.spawn(JobKind::Move(pairs)),
+        };
+        return true;
+    }
+
+    // moves the file in the background. Moves across filesystems
+    // fall back to copying and then deleting the origin.
+    pub fn move_file(&mut self, origin: &Path, dest: &Path) {
+        self.jobs.spawn(JobKind::Move(vec![(
+            origin.to_path_buf(),
+            dest.to_path_buf(),
+        )]));
     }
 
     // load the files of current dir. Prefer calling set_path if updating the path,
     // use this only for reloading the current directory's files.
     pub fn load_dir_entries(&mut self) {
-        self.path = PathBuf::from(&self.path_string);
-        let mut entries = Vec::new();
-
-        match fs::read_dir(&self.path) {
-            Ok(i) => {
-                for entry in i {
-                    let entry: FeEntry = fs_to_fe_entry(entry.unwrap()).unwrap();
-                    entries.push(entry);
-                }
-                self.entries = entries;
+        self.tab_mut().load_entries();
+    }
+
+    // adds the entries loaded in the background to the tabs
+    pub fn poll_loaders(&mut self, ctx: &egui::Context) {
+        let mut loading = false;
+        for tab in &mut self.tabs {
+            let (errors, running) = tab.poll_loader();
+            for err in errors {
+                self.diagnostics.push(Diagnostic::default(err));
+            }
+            loading |= running;
+        }
+        if loading {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+    }
+
+    // reloads the entries of every tab, and the trash in the tabs
+    // showing it.
+    pub fn reload_tabs(&mut self) {
+        let active_tab = self.active_tab;
+        for i in 0..self.tabs.len() {
+            self.active_tab = i;
+            if self.tab().trash_items.is_some() {
+                self.open_trash();
+            } else {
+                self.load_dir_entries();
+            }
+        }
+        self.active_tab = active_tab;
+    }
+
+    // watches the dirs of the tabs, and emits the changes to their entries
+    pub fn watch_dirs(&mut self) {
+        let watcher = match &mut self.watcher {
+            Some(watcher) => watcher,
+            None => return,
+        };
+        let dirs = self
+            .tabs
+            .iter()
+            .filter(|tab| tab.trash_items.is_none())
+            .map(|tab| tab.path.clone())
+            .collect();
+        if let Err(err) = watcher.watch_only(dirs) {
+            self.diagnostics.push(Diagnostic::from_err(&err));
+        }
+        let changes = watcher.poll();
+        if !changes.is_empty() {
+            self.event_pool.emit_event(EventType::DirChanged(changes));
+        }
+    }
 
-                // apply sorting
-                self.update_sorting(self.dir_sorting.clone());
+    // applies the changes reported by the watcher to the tabs showing
+    // their dirs, all at once so each tab is filtered only once
+    pub fn apply_dir_changes(&mut self, changes: Vec<DirChange>) {
+        for tab in &mut self.tabs {
+            if tab.trash_items.is_some() {
+                continue;
             }
-            Err(err) => {
-                self.diagnostics.push(Diagnostic::from_err(&err));
-                println!("error reading entries: {:?}", err)
+            let tab_changes: Vec<DirChange> = changes
+                .iter()
+                .filter(|change| change.dir() == Some(tab.path.as_path()))
+                .cloned()
+                .collect();
+            if !tab_changes.is_empty() {
+                tab.apply_changes(&tab_changes);
             }
         }
-        self.update_display_entries();
     }
 
     // update the sorting without reloading files from the file system
     pub fn update_sorting(&mut self, sort: DirSorting) {
-        self.dir_sorting = sort;
-        self.entries
-            .sort_by(|a, b| utils::dir::compare_entries(a, b, &self.dir_sorting));
+        self.tab_mut().update_sorting(sort);
     }
 
     // drawing
+    // returns how many rows fit in the file table
+    pub fn page_len(&self) -> usize {
+        return (TABLE_HEIGHT / self.style.row_height).max(1.0) as usize;
+    }
+
     pub fn draw_files(&mut self, ui: &mut egui::Ui) {
         let mut hovered_entry = None;
 
@@ -131,10 +308,16 @@ impl FE {
                 .column(Column::auto())
                 .column(Column::remainder())
                 .min_scrolled_height(0.0)
-                .max_scroll_height(600.0);
+                .max_scroll_height(TABLE_HEIGHT);
 
             table = table.sense(egui::Sense::click_and_drag());
 
+            if let Some(index) = self.tab_mut().scroll_to_row.take() {
+                // the ".." row comes first
+                let offset = get_parent(self.tab().path.clone()).is_some() as usize;
+                table = table.scroll_to_row(index + offset, None);
+            }
+
             table
                 .header(20.0, |mut header| {
                     header.col(|ui| {
@@ -143,13 +326,13 @@ impl FE {
                         if ui
                             .button(get_sort_icon(
                                 DirSortingType::FileNameAlphabetically,
-                                &self.dir_sorting,
+                                &self.tab().dir_sorting,
                             ))
                             .clicked()
                         {
                             // if currently sorting by name, toggle it
                             // otherwise, sort by name down
-                            match &self.dir_sorting {
+                            match &self.tab().dir_sorting {
                                 DirSorting::FileNameAlphabetically(dir) => {
                                     self.update_sorting(DirSorting::FileNameAlphabetically(
                                         dir.toggle(),
@@ -166,10 +349,13 @@ impl FE {
                     header.col(|ui| {
                         ui.strong("Size");
                         if ui
-                            .button(get_sort_icon(DirSortingType::FileSize, &self.dir_sorting))
+                            .button(get_sort_icon(
+                                DirSortingType::FileSize,
+                                &self.tab().dir_sorting,
+                            ))
                             .clicked()
                         {
-                            match &self.dir_sorting {
+                            match &self.tab().dir_sorting {
                                 DirSorting::FileSize(dir) => {
                                     self.update_sorting(DirSorting::FileSize(dir.toggle()))
                                 }
@@ -182,11 +368,11 @@ impl FE {
                         if ui
                             .button(get_sort_icon(
                                 DirSortingType::LastModified,
-                                &self.dir_sorting,
+                                &self.tab().dir_sorting,
                             ))
                             .clicked()
                         {
-                            match &self.dir_sorting {
+                            match &self.tab().dir_sorting {
                                 DirSorting::LastModified(dir) => {
                                     self.update_sorting(DirSorting::LastModified(dir.toggle()))
                                 }
@@ -195,32 +381,79 @@ impl FE {
                         }
                     });
                 })
-                .body(|mut body| {
-                    if draw::file::draw_back_dir_row(
-                        &mut body,
-                        self.path.clone(),
-                        &self.style,
-                        &mut self.event_pool,
-                        &self.commands,
-                    ) {
-                        hovered_entry = get_parent(self.path.clone())
-                    };
-
-                    for entry in &self.display_entries {
+                .body(|body| {
+                    let tab = &self.tabs[self.active_tab];
+                    let parent = get_parent(tab.path.clone());
+                    // the ".." row comes first
+                    let offset = parent.is_some() as usize;
+                    let display = tab.display_entries();
+                    // collected once a selected row is visible
+                    let mut selected = None;
+                    let prefix = tab.type_ahead.prefix(Instant::now());
+
+                    // only the visible rows are drawn, so huge dirs stay fast
+                    let rows = offset + display.len();
+                    body.rows(self.style.row_height, rows, |mut row| {
+                        let index = row.index();
+                        if let Some(parent) = parent.as_ref().filter(|_| index < offset) {
+                            if draw::file::draw_back_dir_row(
+                                &mut row,
+                                parent,
+                                &self.style,
+                                &mut self.event_pool,
+                                &self.commands,
+                            ) {
+                                hovered_entry = Some(parent.clone());
+                            }
+                            return;
+                        }
+                        let entry = match display.get(index - offset) {
+                            Some(entry) => entry,
+                            None => return,
+                        };
+
+                        let mut state = RowState::new(entry);
+                        if tab.selection.contains(&entry.path) {
+                            state.targets = selected
+                                .get_or_insert_with(|| tab.selected_entries())
+                                .clone();
+                            state.selected = true;
+                        }
+                        // the type-ahead prefix replaces the search highlight
+                        state.highlight = match prefix {
+                            Some(prefix) => {
+                                (0..matched_len(&entry.name.to_string_lossy(), prefix)).collect()
+                            }
+                            None => tab
+                                .display_highlights
+                                .get(index - offset)
+                                .cloned()
+                                .unwrap_or_default(),
+                        };
                         if draw::file::draw_file_row(
-                            &mut body,
+                            &mut row,
                             entry,
+                            &state,
                             &self.style,
                             &mut self.event_pool,
                             &self.commands,
+                            &mut self.renaming,
                         ) {
                             hovered_entry = Some(entry.clone());
                         };
-                    }
+                    });
                 });
         });
 
-        self.hovered_file = hovered_entry;
+        // with split panes, the other pane may have set it
+        if hovered_entry.is_some() {
+            self.hovered_file = hovered_entry;
+        }
+
+        // repaint when the type-ahead prefix times out to clear the highlight
+        if self.tab().type_ahead.prefix(Instant::now()).is_some() {
+            ui.ctx().request_repaint_after(TypeAhead::TIMEOUT);
+        }
     }
 }
 
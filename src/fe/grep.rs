@@ -0,0 +1,528 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use egui_extras::{Column, TableBuilder};
+use regex::{Regex, RegexBuilder};
+
+use super::search::{location, SearchOptions, TreeWalker};
+use super::FE;
+use crate::events::EventType;
+use crate::utils::dir::{fs_to_fe_entry, EntryKind, FeEntry};
+
+// the search stops after finding that many lines
+const MAX_MATCHES: usize = 10_000;
+// files with a NUL byte in their first bytes are binary
+const BINARY_CHECK_LEN: usize = 8 * 1024;
+// matching lines are cut after that many chars
+const MAX_LINE_LEN: usize = 300;
+
+// GrepOptions are how the pattern is matched against the lines
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GrepOptions {
+    // whether the pattern is a regex instead of literal text
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+// GrepMatch is a line of a file matching the pattern
+#[derive(Debug, Clone)]
+pub struct GrepMatch {
+    pub entry: FeEntry,
+    // the dir of the file, relative to where the search started
+    pub location: String,
+    // starts at 1
+    pub line_number: usize,
+    pub line: String,
+}
+
+// builds the regex matching the pattern with the options
+pub fn grep_regex(pattern: &str, options: &GrepOptions) -> Result<Regex, String> {
+    let pattern = match options.regex {
+        true => pattern.to_string(),
+        false => regex::escape(pattern),
+    };
+    return RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|err| format!("invalid regex: {}", err));
+}
+
+// GrepSearch looks for lines matching a regex in the files under a dir.
+// One thread walks the tree while workers read the files it finds.
+pub struct GrepSearch {
+    pub matches: Vec<GrepMatch>,
+    pub running: bool,
+    receiver: Receiver<Vec<GrepMatch>>,
+    cancelled: Arc<AtomicBool>,
+    dirs_scanned: Arc<AtomicU64>,
+    files_scanned: Arc<AtomicU64>,
+}
+
+impl GrepSearch {
+    pub fn start(root: PathBuf, regex: Regex, options: SearchOptions) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let dirs_scanned = Arc::new(AtomicU64::new(0));
+        let files_scanned = Arc::new(AtomicU64::new(0));
+        let found = Arc::new(AtomicU64::new(0));
+
+        // bounded, so the walk doesn't run far ahead of the workers
+        let (file_sender, file_receiver) = mpsc::sync_channel::<FeEntry>(256);
+        let file_receiver = Arc::new(Mutex::new(file_receiver));
+        let workers = thread::available_parallelism().map_or(4, |n| n.get().min(8));
+        for _ in 0..workers {
+            let worker = Worker {
+                root: root.clone(),
+                regex: regex.clone(),
+                sender: sender.clone(),
+                cancelled: cancelled.clone(),
+                files_scanned: files_scanned.clone(),
+                found: found.clone(),
+            };
+            let file_receiver = file_receiver.clone();
+            thread::spawn(move || loop {
+                // the lock is held while waiting for a file, not while grepping it
+                let entry = match file_receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => return,
+                };
+                match entry {
+                    Ok(entry) => {
+                        if !worker.grep(entry) {
+                            return;
+                        }
+                    }
+                    // the walk is over
+                    Err(_) => return,
+                }
+            });
+        }
+
+        let walker = TreeWalker {
+            options,
+            cancelled: cancelled.clone(),
+            dirs_scanned: dirs_scanned.clone(),
+        };
+        thread::spawn(move || {
+            walker.walk(&root, &mut |entries| {
+                for entry in entries {
+                    if !entry.file_type().is_ok_and(|t| t.is_file()) {
+                        continue;
+                    }
+                    let fe_entry = match fs_to_fe_entry(entry) {
+                        Ok(fe_entry) => fe_entry,
+                        Err(_) => continue,
+                    };
+                    // the workers are gone once the search is over
+                    if file_sender.send(fe_entry).is_err() {
+                        return false;
+                    }
+                }
+                return true;
+            });
+        });
+
+        return Self {
+            matches: Vec::new(),
+            running: true,
+            receiver,
+            cancelled,
+            dirs_scanned,
+            files_scanned,
+        };
+    }
+
+    // collects the matches found since the last call
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(matches) => self.matches.extend(matches),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.running = false;
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn dirs_scanned(&self) -> u64 {
+        return self.dirs_scanned.load(Ordering::Relaxed);
+    }
+
+    pub fn files_scanned(&self) -> u64 {
+        return self.files_scanned.load(Ordering::Relaxed);
+    }
+}
+
+impl Drop for GrepSearch {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// Worker greps the files sent by the walk
+struct Worker {
+    root: PathBuf,
+    regex: Regex,
+    sender: Sender<Vec<GrepMatch>>,
+    cancelled: Arc<AtomicBool>,
+    files_scanned: Arc<AtomicU64>,
+    // shared by the workers
+    found: Arc<AtomicU64>,
+}
+
+impl Worker {
+    // sends the matching lines of the file. Returns false once the search
+    // should stop.
+    fn grep(&self, entry: FeEntry) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.files_scanned.fetch_add(1, Ordering::Relaxed);
+        let lines = match grep_file(&entry.path, &self.regex) {
+            Ok(lines) if !lines.is_empty() => lines,
+            // unreadable and binary files are skipped
+            _ => return true,
+        };
+
+        let found = self.found.fetch_add(lines.len() as u64, Ordering::Relaxed) as usize;
+        if found >= MAX_MATCHES {
+            return false;
+        }
+        let location = location(&self.root, &entry.path);
+        let matches: Vec<GrepMatch> = lines
+            .into_iter()
+            .take(MAX_MATCHES - found)
+            .map(|(line_number, line)| GrepMatch {
+                entry: entry.clone(),
+                location: location.clone(),
+                line_number,
+                line,
+            })
+            .collect();
+        // the search was dropped if nobody listens anymore
+        return self.sender.send(matches).is_ok();
+    }
+}
+
+// returns the numbers and text of the lines matching the regex. Binary
+// files have no matches.
+pub fn grep_file(path: &Path, regex: &Regex) -> io::Result<Vec<(usize, String)>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut head = Vec::new();
+    reader
+        .by_ref()
+        .take(BINARY_CHECK_LEN as u64)
+        .read_to_end(&mut head)?;
+    if head.contains(&0) {
+        return Ok(Vec::new());
+    }
+
+    let mut reader = io::Cursor::new(head).chain(reader);
+    let mut lines = Vec::new();
+    let mut buf = Vec::new();
+    let mut line_number = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        line_number += 1;
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if regex.is_match(line) {
+            lines.push((
+                line_number,
+                line.trim().chars().take(MAX_LINE_LEN).collect(),
+            ));
+        }
+    }
+    return Ok(lines);
+}
+
+// Grep is the state of the panel searching file contents
+#[derive(Default)]
+pub struct Grep {
+    pub pattern: String,
+    pub options: GrepOptions,
+    pub search_options: SearchOptions,
+    pub search: Option<GrepSearch>,
+    // why the pattern can't be searched
+    pub error: Option<String>,
+    // set when the panel opens, to focus the pattern input
+    focus: bool,
+}
+
+impl FE {
+    pub fn toggle_grep(&mut self) {
+        self.grep = match self.grep {
+            Some(_) => None,
+            None => Some(Grep {
+                focus: true,
+                ..Grep::default()
+            }),
+        };
+    }
+
+    // greps the current dir with the pattern of the panel
+    fn start_grep(&mut self) {
+        let root = self.tab().path.clone();
+        let grep = match &mut self.grep {
+            Some(grep) => grep,
+            None => return,
+        };
+        grep.search = None;
+        if grep.pattern.is_empty() {
+            grep.error = None;
+            return;
+        }
+        match grep_regex(&grep.pattern, &grep.options) {
+            Ok(regex) => {
+                grep.error = None;
+                grep.search = Some(GrepSearch::start(root, regex, grep.search_options.clone()));
+            }
+            Err(err) => grep.error = Some(err),
+        }
+    }
+
+    // draws the panel at the bottom of the window
+    pub fn draw_grep(&mut self, ctx: &egui::Context) {
+        if self.grep.is_none() {
+            return;
+        }
+        let mut start = false;
+        let mut close = false;
+        egui::TopBottomPanel::bottom("grep")
+            .resizable(true)
+            .default_height(250.0)
+            .show(ctx, |ui| {
+                let grep = match &mut self.grep {
+                    Some(grep) => grep,
+                    None => return,
+                };
+                ui.horizontal(|ui| {
+                    ui.label("Search in files:");
+                    let input = ui.text_edit_singleline(&mut grep.pattern);
+                    if grep.focus {
+                        input.request_focus();
+                        grep.focus = false;
+                    }
+                    if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        start = true;
+                    }
+                    start |= ui.checkbox(&mut grep.options.regex, "Regex").changed();
+                    start |= ui
+                        .checkbox(&mut grep.options.case_sensitive, "Match case")
+                        .changed();
+                    ui.menu_button("⚙", |ui| {
+                        let options = &mut grep.search_options;
+                        ui.horizontal(|ui| {
+                            ui.label("Max depth");
+                            start |= ui
+                                .add(
+                                    egui::DragValue::new(&mut options.max_depth)
+                                        .clamp_range(1..=64),
+                                )
+                                .changed();
+                        });
+                        start |= ui
+                            .checkbox(&mut options.skip_hidden, "Skip hidden files")
+                            .changed();
+                        start |= ui
+                            .checkbox(&mut options.respect_gitignore, "Skip .gitignored files")
+                            .changed();
+                    });
+                    if ui.button("Search").clicked() {
+                        start = true;
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("x").clicked() {
+                            close = true;
+                        }
+                    });
+                });
+                if let Some(err) = &grep.error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+
+                let search = match &mut grep.search {
+                    Some(search) => search,
+                    None => return,
+                };
+                search.poll();
+                ui.horizontal(|ui| {
+                    if search.running {
+                        ui.spinner();
+                        ui.label(format!(
+                            "Searching... {} dirs, {} files, {} found",
+                            search.dirs_scanned(),
+                            search.files_scanned(),
+                            search.matches.len()
+                        ));
+                        if ui.button("Cancel").clicked() {
+                            search.cancel();
+                        }
+                    } else if search.matches.len() >= MAX_MATCHES {
+                        ui.label(format!("Showing the first {} matches", MAX_MATCHES));
+                    } else {
+                        ui.label(format!(
+                            "{} found in {} files",
+                            search.matches.len(),
+                            search.files_scanned()
+                        ));
+                    }
+                });
+
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .resizable(false)
+                    .sense(egui::Sense::click())
+                    .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+                    .column(Column::auto())
+                    .column(Column::auto())
+                    .column(Column::remainder().clip(true))
+                    .max_scroll_height(f32::INFINITY)
+                    .header(20.0, |mut header| {
+                        header.col(|ui| {
+                            ui.strong("File");
+                        });
+                        header.col(|ui| {
+                            ui.strong("Line");
+                        });
+                        header.col(|ui| {
+                            ui.strong("Text");
+                        });
+                    })
+                    .body(|body| {
+                        body.rows(self.style.row_height, search.matches.len(), |mut row| {
+                            let m = &search.matches[row.index()];
+                            row.col(|ui| {
+                                let name = m.entry.name.to_string_lossy();
+                                ui.label(format!("{}/{}", m.location, name))
+                                    .on_hover_text(m.entry.path.to_string_lossy());
+                            });
+                            row.col(|ui| {
+                                ui.weak(m.line_number.to_string());
+                            });
+                            row.col(|ui| {
+                                ui.monospace(&m.line);
+                            });
+
+                            let response = row.response();
+                            if response.double_clicked() {
+                                self.event_pool
+                                    .emit_event(open_event(&m.entry, &self.commands));
+                            }
+                            response.context_menu(|ui| {
+                                if ui.button("Open").clicked() {
+                                    self.event_pool
+                                        .emit_event(open_event(&m.entry, &self.commands));
+                                    ui.close_menu();
+                                }
+                                if ui.button("Show in folder").clicked() {
+                                    self.event_pool
+                                        .emit_event(EventType::RevealEntry(m.entry.path.clone()));
+                                    ui.close_menu();
+                                }
+                            });
+                        });
+                    });
+
+                // keep polling while it runs
+                if search.running {
+                    ui.ctx().request_repaint();
+                }
+            });
+
+        if close {
+            self.grep = None;
+        } else if start {
+            self.start_grep();
+        }
+    }
+}
+
+// runs the clickable command for the file type, or opens the file with
+// the default application
+fn open_event(entry: &FeEntry, commands: &crate::commands::Commands) -> EventType {
+    if let EntryKind::File(file) = &entry.entry_type {
+        // executables are opened rather than run, they matched as text
+        if let Some(event @ EventType::RunFileCmd(..)) = file.is_clickable(&commands.file) {
+            return event;
+        }
+    }
+    return EventType::OpenDefault(entry.path.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    // waits for the search to finish and returns the matches as
+    // `path:line:text`
+    fn grep(root: &Path, pattern: &str, options: GrepOptions) -> Vec<String> {
+        let regex = grep_regex(pattern, &options).unwrap();
+        let mut search = GrepSearch::start(root.to_path_buf(), regex, SearchOptions::default());
+        let start = Instant::now();
+        while search.running && start.elapsed() < Duration::from_secs(10) {
+            search.poll();
+            thread::sleep(Duration::from_millis(1));
+        }
+        let mut matches: Vec<String> = search
+            .matches
+            .iter()
+            .map(|m| {
+                let name = m.entry.name.to_string_lossy();
+                format!("{}/{}:{}:{}", m.location, name, m.line_number, m.line)
+            })
+            .collect();
+        matches.sort();
+        return matches;
+    }
+
+    #[test]
+    fn test_grep() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("notes.txt"), "todo: write tests\nDone\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {\n    // TODO(x)\n}\n").unwrap();
+        fs::write(root.join("image.bin"), b"todo\0\x01\x02").unwrap();
+
+        let options = GrepOptions::default();
+        assert_eq!(
+            grep(root, "todo", options.clone()),
+            vec![
+                "./notes.txt:1:todo: write tests",
+                "src/main.rs:2:// TODO(x)"
+            ]
+        );
+        let case_sensitive = GrepOptions {
+            case_sensitive: true,
+            ..options.clone()
+        };
+        assert_eq!(grep(root, "TODO", case_sensitive).len(), 1);
+        // literal patterns aren't regexes
+        assert_eq!(grep(root, "TODO(x)", options.clone()).len(), 1);
+        assert!(grep(root, "t.do", options).is_empty());
+
+        let regex = GrepOptions {
+            regex: true,
+            case_sensitive: false,
+        };
+        assert_eq!(grep(root, "^(fn|done)", regex.clone()).len(), 2);
+        assert!(grep_regex("(", &regex).is_err());
+    }
+}
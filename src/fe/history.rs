@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+// History is the list of dirs visited in a tab, to go back and forward
+// like in a browser.
+#[derive(Debug, Default)]
+pub struct History {
+    // most recently visited last
+    back: Vec<PathBuf>,
+    // most recently left last
+    forward: Vec<PathBuf>,
+}
+
+impl History {
+    const MAX_LEN: usize = 100;
+
+    // records leaving `path` for a new dir. Dirs that were left by going
+    // back can't be gone forward to after that.
+    pub fn push(&mut self, path: PathBuf) {
+        if self.back.last() == Some(&path) {
+            return;
+        }
+        self.back.push(path);
+        if self.back.len() > Self::MAX_LEN {
+            self.back.remove(0);
+        }
+        self.forward.clear();
+    }
+
+    // goes back `steps` dirs from `current`, returning the dir to open
+    pub fn go_back(&mut self, current: PathBuf, steps: usize) -> Option<PathBuf> {
+        return travel(&mut self.back, &mut self.forward, current, steps);
+    }
+
+    // goes forward `steps` dirs from `current`, returning the dir to open
+    pub fn go_forward(&mut self, current: PathBuf, steps: usize) -> Option<PathBuf> {
+        return travel(&mut self.forward, &mut self.back, current, steps);
+    }
+
+    // returns the dirs to go back to, most recent first
+    pub fn back_list(&self) -> impl Iterator<Item = &PathBuf> {
+        return self.back.iter().rev();
+    }
+
+    // returns the dirs to go forward to, nearest first
+    pub fn forward_list(&self) -> impl Iterator<Item = &PathBuf> {
+        return self.forward.iter().rev();
+    }
+}
+
+// pops `steps` dirs from `from`, pushing `current` and the skipped dirs
+// into `to`.
+fn travel(
+    from: &mut Vec<PathBuf>,
+    to: &mut Vec<PathBuf>,
+    current: PathBuf,
+    steps: usize,
+) -> Option<PathBuf> {
+    if steps == 0 || steps > from.len() {
+        return None;
+    }
+    to.push(current);
+    for _ in 1..steps {
+        to.push(from.pop()?);
+    }
+    return from.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let (a, b, c, d) = (
+            PathBuf::from("/a"),
+            PathBuf::from("/b"),
+            PathBuf::from("/c"),
+            PathBuf::from("/d"),
+        );
+        let mut history = History::default();
+        assert_eq!(history.go_back(a.clone(), 1), None);
+
+        // visit a, b, c, then d
+        history.push(a.clone());
+        history.push(b.clone());
+        history.push(c.clone());
+        assert_eq!(history.back_list().collect::<Vec<_>>(), vec![&c, &b, &a]);
+
+        assert_eq!(history.go_back(d.clone(), 1), Some(c.clone()));
+        assert_eq!(history.go_back(c.clone(), 2), Some(a.clone()));
+        assert_eq!(history.forward_list().collect::<Vec<_>>(), vec![&b, &c, &d]);
+        assert_eq!(history.go_back(a.clone(), 1), None);
+
+        assert_eq!(history.go_forward(a.clone(), 2), Some(c.clone()));
+        assert_eq!(history.back_list().collect::<Vec<_>>(), vec![&b, &a]);
+        assert_eq!(history.forward_list().collect::<Vec<_>>(), vec![&d]);
+
+        // visiting a new dir drops the forward history
+        history.push(c.clone());
+        assert_eq!(history.forward_list().count(), 0);
+        assert_eq!(history.go_forward(a.clone(), 1), None);
+    }
+}
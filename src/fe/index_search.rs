@@ -0,0 +1,367 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use chrono::Utc;
+
+use super::diagnostic::Diagnostic;
+use super::search::{SearchOptions, TreeWalker};
+use super::FE;
+use crate::events::EventType;
+use crate::storage::Storage;
+use crate::utils;
+use crate::utils::file_index::{IndexChanges, IndexRoot, IndexedFile};
+
+// how many matches the index search lists
+const MAX_RESULTS: usize = 200;
+// how often to look for roots due for a refresh
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// refresh interval when the config doesn't set one
+pub const DEFAULT_REFRESH_MINUTES: u64 = 30;
+
+// Indexer refreshes the index of the roots on a worker thread. It writes
+// through its own connection to the db.
+pub struct Indexer {
+    receiver: Receiver<String>,
+    cancelled: Arc<AtomicBool>,
+    files_scanned: Arc<AtomicU64>,
+}
+
+impl Indexer {
+    // refreshes the roots indexed longer than `refresh` ago, or every root
+    // if `force` is set
+    pub fn start(data_path: PathBuf, refresh: Duration, force: bool) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let files_scanned = Arc::new(AtomicU64::new(0));
+
+        let walker = TreeWalker {
+            // like the searches of the tabs, but hidden and ignored files
+            // can be looked up too
+            options: SearchOptions {
+                max_depth: 64,
+                skip_hidden: false,
+                respect_gitignore: false,
+            },
+            cancelled: cancelled.clone(),
+            dirs_scanned: Arc::new(AtomicU64::new(0)),
+        };
+        let scanned = files_scanned.clone();
+        thread::spawn(move || {
+            let storage = match Storage::new(data_path) {
+                Ok(storage) => storage,
+                Err(err) => {
+                    let _ = sender.send(err.to_string());
+                    return;
+                }
+            };
+            let roots = match storage.list_index_roots() {
+                Ok(roots) => roots,
+                Err(err) => {
+                    let _ = sender.send(err.to_string());
+                    return;
+                }
+            };
+            let now = Utc::now().timestamp();
+            for root in roots {
+                if !force && now - root.last_indexed < refresh.as_secs() as i64 {
+                    continue;
+                }
+                if let Err(err) = refresh_root(&storage, &root.path, &walker, &scanned) {
+                    let _ = sender.send(format!(
+                        "can't index {}: {}",
+                        root.path.to_string_lossy(),
+                        err
+                    ));
+                }
+                if walker.cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+            }
+        });
+
+        return Self {
+            receiver,
+            cancelled,
+            files_scanned,
+        };
+    }
+
+    // returns the errors since the last call, and whether it still runs
+    pub fn poll(&self) -> (Vec<String>, bool) {
+        let mut errors = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(err) => errors.push(err),
+                Err(TryRecvError::Empty) => return (errors, true),
+                Err(TryRecvError::Disconnected) => return (errors, false),
+            }
+        }
+    }
+
+    pub fn files_scanned(&self) -> u64 {
+        return self.files_scanned.load(Ordering::Relaxed);
+    }
+
+    // stops the refresh. It may still be writing, see `poll`.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Indexer {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// walks the root and writes what changed since its last refresh
+fn refresh_root(
+    storage: &Storage,
+    root: &Path,
+    walker: &TreeWalker,
+    scanned: &AtomicU64,
+) -> Result<(), String> {
+    if !root.is_dir() {
+        return Err("no such directory".to_string());
+    }
+    let indexed = storage
+        .list_indexed_files(root)
+        .map_err(|err| err.to_string())?;
+    let mut found = Vec::new();
+    walker.walk(root, &mut |entries| {
+        scanned.fetch_add(entries.len() as u64, Ordering::Relaxed);
+        found.extend(entries.iter().filter_map(IndexedFile::from_dir_entry));
+        return true;
+    });
+    // files missing from a partial walk aren't gone
+    if walker.cancelled.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    let changes = IndexChanges::diff(indexed, found);
+    return storage
+        .update_index(root, &changes, Utc::now().timestamp())
+        .map_err(|err| err.to_string());
+}
+
+// IndexSearch is the state of the dialog searching the file index
+#[derive(Default)]
+pub struct IndexSearch {
+    query: String,
+    results: Vec<IndexedFile>,
+    cursor: usize,
+    roots: Vec<IndexRoot>,
+}
+
+impl FE {
+    // starts a refresh of the index if one is due. `force` refreshes
+    // every root, as soon as the running refresh stopped writing.
+    pub fn refresh_index(&mut self, ctx: &egui::Context, force: bool) {
+        if force {
+            self.force_index = true;
+            if let Some(indexer) = &self.indexer {
+                indexer.cancel();
+            }
+        }
+        if let Some(indexer) = &self.indexer {
+            let (errors, running) = indexer.poll();
+            for err in errors {
+                self.diagnostics.push(Diagnostic::default(err));
+            }
+            if running {
+                ctx.request_repaint_after(Duration::from_millis(250));
+                return;
+            }
+            self.indexer = None;
+            self.load_index_roots();
+        }
+
+        let now = Instant::now();
+        if self.force_index || now >= self.next_index_check {
+            self.next_index_check = now + CHECK_INTERVAL;
+            self.indexer = Some(Indexer::start(
+                self.data_path.clone(),
+                self.index_refresh,
+                std::mem::take(&mut self.force_index),
+            ));
+            ctx.request_repaint_after(Duration::from_millis(250));
+        } else {
+            ctx.request_repaint_after(self.next_index_check - now);
+        }
+    }
+
+    // opens the dialog to search the file index
+    pub fn open_index_search(&mut self) {
+        self.index_search = Some(IndexSearch::default());
+        self.load_index_roots();
+    }
+
+    fn load_index_roots(&mut self) {
+        let search = match &mut self.index_search {
+            Some(search) => search,
+            None => return,
+        };
+        match self.storage.list_index_roots() {
+            Ok(roots) => search.roots = roots,
+            Err(err) => self.diagnostics.push(Diagnostic::from_err(&err)),
+        }
+    }
+
+    fn update_index_results(&mut self) {
+        let search = match &mut self.index_search {
+            Some(search) => search,
+            None => return,
+        };
+        match self.storage.search_index(&search.query, MAX_RESULTS) {
+            Ok(results) => search.results = results,
+            Err(err) => self.diagnostics.push(Diagnostic::from_err(&err)),
+        }
+        search.cursor = 0;
+    }
+
+    pub fn draw_index_search(&mut self, ctx: &egui::Context) {
+        let current_dir = self.tab().path.clone();
+        let indexing = self.indexer.as_ref().map(|indexer| indexer.files_scanned());
+        let search = match &mut self.index_search {
+            Some(search) => search,
+            None => return,
+        };
+
+        let mut open = true;
+        let mut changed = false;
+        let mut picked = None;
+        let mut added_root = None;
+        let mut removed_root = None;
+        let mut reindex = false;
+        egui::Window::new("Search index")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                let input = ui.text_edit_singleline(&mut search.query);
+                input.request_focus();
+                changed = input.changed();
+
+                // the arrows move through the results while typing
+                ui.input_mut(|i| {
+                    if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+                        search.cursor += 1;
+                    }
+                    if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                        search.cursor = search.cursor.saturating_sub(1);
+                    }
+                });
+                search.cursor = search.cursor.min(search.results.len().saturating_sub(1));
+                if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    picked = search.results.get(search.cursor).cloned();
+                }
+
+                ui.separator();
+                if search.roots.is_empty() {
+                    ui.weak("No dir is indexed yet, add one below");
+                } else if search.results.is_empty() && !search.query.trim().is_empty() {
+                    ui.weak("No indexed file matches");
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (i, file) in search.results.iter().enumerate() {
+                            let icon = if file.is_dir { "🗀" } else { "🗋" };
+                            let parent = file.path.parent().unwrap_or(&file.path);
+                            let label = ui
+                                .selectable_label(
+                                    i == search.cursor,
+                                    format!(
+                                        "{} {}    {}",
+                                        icon,
+                                        file.name,
+                                        parent.to_string_lossy()
+                                    ),
+                                )
+                                .on_hover_text(utils::human_readable_size(file.size));
+                            if label.clicked() {
+                                picked = Some(file.clone());
+                            }
+                        }
+                    });
+
+                ui.separator();
+                egui::CollapsingHeader::new("Indexed dirs").show(ui, |ui| {
+                    for root in &search.roots {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .small_button("x")
+                                .on_hover_text("Stop indexing")
+                                .clicked()
+                            {
+                                removed_root = Some(root.path.clone());
+                            }
+                            ui.label(root.path.to_string_lossy());
+                            let indexed = match root.last_indexed {
+                                0 => "not indexed yet".to_string(),
+                                time => utils::system_time_to_human_readable(
+                                    UNIX_EPOCH + Duration::from_secs(time as u64),
+                                ),
+                            };
+                            ui.weak(indexed);
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        let is_root = search.roots.iter().any(|r| r.path == current_dir);
+                        if ui
+                            .add_enabled(!is_root, egui::Button::new("Index current dir"))
+                            .clicked()
+                        {
+                            added_root = Some(current_dir.clone());
+                        }
+                        match indexing {
+                            Some(files) => {
+                                ui.spinner();
+                                ui.label(format!("Indexing... {} files", files));
+                            }
+                            None => reindex = ui.button("Refresh now").clicked(),
+                        }
+                    });
+                });
+            });
+
+        let escape = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        if let Some(file) = picked {
+            let event = match file.is_dir {
+                true => EventType::SetPath(file.path),
+                false => EventType::RevealEntry(file.path),
+            };
+            self.event_pool.emit_event(event);
+            self.index_search = None;
+            return;
+        } else if !open || escape {
+            self.index_search = None;
+            return;
+        }
+
+        if changed {
+            self.update_index_results();
+        }
+        if let Some(path) = added_root {
+            if let Err(err) = self.storage.add_index_root(&path) {
+                self.diagnostics.push(Diagnostic::from_err(&err));
+            }
+            self.load_index_roots();
+            reindex = true;
+        }
+        if let Some(path) = removed_root {
+            if let Err(err) = self.storage.remove_index_root(&path) {
+                self.diagnostics.push(Diagnostic::from_err(&err));
+            }
+            self.load_index_roots();
+            self.update_index_results();
+        }
+        if reindex {
+            self.refresh_index(ctx, true);
+        }
+    }
+}
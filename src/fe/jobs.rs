@@ -0,0 +1,773 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use super::diagnostic::Diagnostic;
+use super::journal::Operation;
+use crate::utils::trash::{self, TrashItem};
+
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+pub type JobId = usize;
+
+// JobKind is the file operation run by a job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobKind {
+    // (origin, destination) pairs
+    Copy(Vec<(PathBuf, PathBuf)>),
+    Move(Vec<(PathBuf, PathBuf)>),
+    Delete(Vec<PathBuf>),
+    Trash(Vec<PathBuf>),
+    // permanently deletes the items from the trash
+    EmptyTrash(Vec<TrashItem>),
+    Undo(Operation),
+    Redo(Operation),
+}
+
+impl JobKind {
+    fn describe(&self) -> String {
+        let (verb, paths): (&str, Vec<&PathBuf>) = match self {
+            JobKind::Copy(pairs) => ("Copying", pairs.iter().map(|(from, _)| from).collect()),
+            JobKind::Move(pairs) => ("Moving", pairs.iter().map(|(from, _)| from).collect()),
+            JobKind::Delete(paths) => ("Deleting", paths.iter().collect()),
+            JobKind::Trash(paths) => ("Trashing", paths.iter().collect()),
+            JobKind::EmptyTrash(_) => ("Emptying trash", Vec::new()),
+            JobKind::Undo(op) => ("Undoing", op.paths()),
+            JobKind::Redo(op) => ("Redoing", op.paths()),
+        };
+
+        let name = |p: &PathBuf| {
+            p.file_name()
+                .unwrap_or(p.as_os_str())
+                .to_string_lossy()
+                .to_string()
+        };
+        return match paths.as_slice() {
+            [] => verb.to_string(),
+            [path] => format!("{} {}", verb, name(path)),
+            [path, rest @ ..] => format!("{} {} and {} more", verb, name(path), rest.len()),
+        };
+    }
+}
+
+// JobProgress is shared between the UI and the worker running the job.
+#[derive(Debug, Default)]
+pub struct JobProgress {
+    bytes_done: AtomicU64,
+    bytes_total: AtomicU64,
+    files_done: AtomicU64,
+    files_total: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl JobProgress {
+    // returns the (done, total) bytes
+    pub fn bytes(&self) -> (u64, u64) {
+        (
+            self.bytes_done.load(Ordering::Relaxed),
+            self.bytes_total.load(Ordering::Relaxed),
+        )
+    }
+
+    // returns the (done, total) files
+    pub fn files(&self) -> (u64, u64) {
+        (
+            self.files_done.load(Ordering::Relaxed),
+            self.files_total.load(Ordering::Relaxed),
+        )
+    }
+
+    // returns the progress between 0 and 1, by bytes if there are
+    // any to process, by files otherwise.
+    pub fn fraction(&self) -> f32 {
+        let (bytes_done, bytes_total) = self.bytes();
+        if bytes_total > 0 {
+            return (bytes_done as f32 / bytes_total as f32).min(1.0);
+        }
+        let (files_done, files_total) = self.files();
+        if files_total > 0 {
+            return (files_done as f32 / files_total as f32).min(1.0);
+        }
+        return 0.0;
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return self.cancelled.load(Ordering::Relaxed);
+    }
+
+    fn add_total(&self, files: u64, bytes: u64) {
+        self.files_total.fetch_add(files, Ordering::Relaxed);
+        self.bytes_total.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn add_done(&self, files: u64, bytes: u64) {
+        self.files_done.fetch_add(files, Ordering::Relaxed);
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Done,
+    Cancelled,
+    Failed(String),
+}
+
+// JobResult is sent back to the UI once a job finishes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobResult {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub description: String,
+    pub status: JobStatus,
+}
+
+pub struct Job {
+    pub id: JobId,
+    // the job's description and progress, as shown in the jobs panel
+    pub status: Diagnostic,
+    pub progress: Arc<JobProgress>,
+}
+
+// JobQueue runs file operations on worker threads, so they don't
+// block the UI.
+pub struct JobQueue {
+    jobs: Vec<Job>,
+    next_id: JobId,
+    sender: Sender<JobResult>,
+    receiver: Receiver<JobResult>,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            jobs: Vec::new(),
+            next_id: 0,
+            sender,
+            receiver,
+        }
+    }
+
+    // starts the job on a new worker thread
+    pub fn spawn(&mut self, kind: JobKind) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let description = kind.describe();
+        let progress = Arc::new(JobProgress::default());
+
+        let sender = self.sender.clone();
+        let worker_progress = progress.clone();
+        let worker_description = description.clone();
+        thread::spawn(move || {
+            let status = match run(&kind, &worker_progress) {
+                Ok(()) => JobStatus::Done,
+                Err(_) if worker_progress.is_cancelled() => JobStatus::Cancelled,
+                Err(err) => JobStatus::Failed(err.to_string()),
+            };
+            // the receiver is only dropped when fe is closing
+            let _ = sender.send(JobResult {
+                id,
+                kind,
+                description: worker_description,
+                status,
+            });
+        });
+
+        self.jobs.push(Job {
+            id,
+            status: Diagnostic::from_job(description, progress.clone()),
+            progress,
+        });
+        return id;
+    }
+
+    pub fn jobs(&self) -> &Vec<Job> {
+        return &self.jobs;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.jobs.is_empty();
+    }
+
+    pub fn cancel(&self, id: JobId) {
+        if let Some(job) = self.jobs.iter().find(|j| j.id == id) {
+            job.progress.cancel();
+        }
+    }
+
+    // returns the results of the jobs finished since the last call
+    pub fn poll(&mut self) -> Vec<JobResult> {
+        let results: Vec<JobResult> = self.receiver.try_iter().collect();
+        self.jobs
+            .retain(|job| !results.iter().any(|result| result.id == job.id));
+        return results;
+    }
+}
+
+fn run(kind: &JobKind, progress: &JobProgress) -> io::Result<()> {
+    match kind {
+        JobKind::Copy(pairs) => {
+            for (from, _) in pairs {
+                count(from, progress)?;
+            }
+            for (from, to) in pairs {
+                copy_path(from, to, progress)?;
+            }
+        }
+        JobKind::Move(pairs) => {
+            for (from, to) in pairs {
+                move_path(from, to, progress)?;
+            }
+        }
+        JobKind::Delete(paths) => {
+            for path in paths {
+                count(path, progress)?;
+            }
+            for path in paths {
+                delete_path(path, progress)?;
+            }
+        }
+        JobKind::Trash(paths) => {
+            progress.add_total(paths.len() as u64, 0);
+            for path in paths {
+                check_cancelled(progress)?;
+                trash::trash(path)?;
+                progress.add_done(1, 0);
+            }
+        }
+        JobKind::EmptyTrash(items) => {
+            for item in items {
+                count(&item.path(), progress)?;
+            }
+            for item in items {
+                delete_path(&item.path(), progress)?;
+                trash::forget(item)?;
+            }
+        }
+        JobKind::Undo(op) => undo(op, progress)?,
+        JobKind::Redo(op) => redo(op, progress)?,
+    }
+    return Ok(());
+}
+
+// reverts the operation
+fn undo(op: &Operation, progress: &JobProgress) -> io::Result<()> {
+    match op {
+        Operation::Create { path, .. } | Operation::Link { path, .. } => {
+            // the file may have been written to since, so keep it in the trash
+            return run(&JobKind::Trash(vec![path.clone()]), progress);
+        }
+        Operation::Move(pairs) => {
+            for (from, to) in pairs.iter().rev() {
+                move_path(to, from, progress)?;
+            }
+        }
+        Operation::Copy(pairs) => {
+            let copies = pairs.iter().map(|(_, to)| to.clone()).collect();
+            return run(&JobKind::Trash(copies), progress);
+        }
+        Operation::Trash(paths) => {
+            progress.add_total(paths.len() as u64, 0);
+            for path in paths {
+                check_cancelled(progress)?;
+                trash::restore(&trash::find_trashed(path)?)?;
+                progress.add_done(1, 0);
+            }
+        }
+    }
+    return Ok(());
+}
+
+// does an undone operation again
+fn redo(op: &Operation, progress: &JobProgress) -> io::Result<()> {
+    return match op {
+        Operation::Create { path, is_dir } => {
+            if *is_dir {
+                fs::create_dir(path)
+            } else {
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(path)
+                    .map(|_| ())
+            }
+        }
+        Operation::Move(pairs) => run(&JobKind::Move(pairs.clone()), progress),
+        Operation::Copy(pairs) => run(&JobKind::Copy(pairs.clone()), progress),
+        Operation::Trash(paths) => run(&JobKind::Trash(paths.clone()), progress),
+        Operation::Link { path, target } => create_symlink(target, path),
+    };
+}
+
+fn check_cancelled(progress: &JobProgress) -> io::Result<()> {
+    if progress.is_cancelled() {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+    }
+    return Ok(());
+}
+
+// adds the files and bytes under `path` to the job totals
+fn count(path: &Path, progress: &JobProgress) -> io::Result<()> {
+    check_cancelled(progress)?;
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            count(&entry?.path(), progress)?;
+        }
+        return Ok(());
+    }
+    if metadata.is_symlink() {
+        progress.add_total(1, 0);
+        return Ok(());
+    }
+    progress.add_total(1, metadata.len());
+    return Ok(());
+}
+
+// moves `from` to `to`. Renaming doesn't work across filesystems, so in
+// that case the entry is copied and verified, and only then is `from` deleted.
+fn move_path(from: &Path, to: &Path, progress: &JobProgress) -> io::Result<()> {
+    check_cancelled(progress)?;
+    match fs::rename(from, to) {
+        Ok(()) => {
+            progress.add_total(1, 0);
+            progress.add_done(1, 0);
+            return Ok(());
+        }
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            return move_across_devices(from, to, progress);
+        }
+        Err(err) => return Err(err),
+    }
+}
+
+fn move_across_devices(from: &Path, to: &Path, progress: &JobProgress) -> io::Result<()> {
+    // unlike rename, never replace an existing entry: we wouldn't be
+    // able to tell it apart from a partial copy if something fails.
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+
+    count(from, progress)?;
+    if let Err(err) = copy_path(from, to, progress).and_then(|_| verify_copy(from, to)) {
+        let _ = remove_path(to);
+        return Err(err);
+    }
+    return remove_path(from);
+}
+
+// checks that `to` has the same entries, file contents and links as `from`
+fn verify_copy(from: &Path, to: &Path) -> io::Result<()> {
+    let mismatch = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} doesn't match its copy", from.display()),
+        )
+    };
+    let from_metadata = fs::symlink_metadata(from)?;
+    let to_metadata = fs::symlink_metadata(to)?;
+
+    if from_metadata.is_symlink() {
+        if !to_metadata.is_symlink() || fs::read_link(from)? != fs::read_link(to)? {
+            return Err(mismatch());
+        }
+        return Ok(());
+    }
+    if from_metadata.is_dir() {
+        if !to_metadata.is_dir() {
+            return Err(mismatch());
+        }
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            verify_copy(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    if !to_metadata.is_file()
+        || from_metadata.len() != to_metadata.len()
+        || !same_contents(from, to)?
+    {
+        return Err(mismatch());
+    }
+    return Ok(());
+}
+
+// compares the contents of two files, reading them a chunk at a time
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut a = BufReader::with_capacity(COPY_BUFFER_SIZE, fs::File::open(a)?);
+    let mut b = BufReader::with_capacity(COPY_BUFFER_SIZE, fs::File::open(b)?);
+    loop {
+        let a_buf = a.fill_buf()?;
+        let b_buf = b.fill_buf()?;
+        if a_buf.is_empty() || b_buf.is_empty() {
+            return Ok(a_buf.is_empty() && b_buf.is_empty());
+        }
+        let len = a_buf.len().min(b_buf.len());
+        if a_buf[..len] != b_buf[..len] {
+            return Ok(false);
+        }
+        a.consume(len);
+        b.consume(len);
+    }
+}
+
+// removes `path` without reporting progress
+fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        return fs::remove_dir_all(path);
+    }
+    return fs::remove_file(path);
+}
+
+// copies `from` into `to`. Directories are copied recursively, symlinks
+// are copied as links, and permissions and timestamps are kept.
+pub fn copy_path(from: &Path, to: &Path, progress: &JobProgress) -> io::Result<()> {
+    check_cancelled(progress)?;
+    let metadata = fs::symlink_metadata(from)?;
+
+    if metadata.is_symlink() {
+        copy_symlink(from, to)?;
+        progress.add_done(1, 0);
+        return Ok(());
+    }
+
+    if metadata.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()), progress)?;
+        }
+        // set after copying the children, which would change them
+        // or, if the dir is read only, fail.
+        // Opening dirs isn't supported everywhere (e.g. Windows), so the
+        // timestamps are kept on a best effort basis.
+        if let Ok(dir) = fs::File::open(to) {
+            let _ = dir.set_times(file_times(&metadata)?);
+        }
+        fs::set_permissions(to, metadata.permissions())?;
+        return Ok(());
+    }
+
+    let mut reader = fs::File::open(from)?;
+    let mut writer = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)?;
+    if let Err(err) = copy_contents(&mut reader, &mut writer, progress) {
+        // don't leave half written files behind
+        let _ = fs::remove_file(to);
+        return Err(err);
+    }
+    writer.set_times(file_times(&metadata)?)?;
+    writer.set_permissions(metadata.permissions())?;
+    progress.add_done(1, 0);
+    return Ok(());
+}
+
+fn file_times(metadata: &fs::Metadata) -> io::Result<fs::FileTimes> {
+    return Ok(fs::FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?));
+}
+
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    return create_symlink(&fs::read_link(from)?, to);
+}
+
+// creates a link at `path` pointing to `target`. Relative targets are
+// relative to the link's dir.
+pub fn create_symlink(target: &Path, path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        return std::os::unix::fs::symlink(target, path);
+    }
+
+    #[cfg(windows)]
+    {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        if fs::metadata(dir.join(target))
+            .map(|m| m.is_dir())
+            .unwrap_or(false)
+        {
+            return std::os::windows::fs::symlink_dir(target, path);
+        }
+        return std::os::windows::fs::symlink_file(target, path);
+    }
+}
+
+fn copy_contents(
+    reader: &mut fs::File,
+    writer: &mut fs::File,
+    progress: &JobProgress,
+) -> io::Result<()> {
+    let mut buf = vec![0; COPY_BUFFER_SIZE];
+    loop {
+        check_cancelled(progress)?;
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buf[..read])?;
+        progress.add_done(0, read as u64);
+    }
+}
+
+// deletes `path`, and everything under it if it's a directory
+fn delete_path(path: &Path, progress: &JobProgress) -> io::Result<()> {
+    check_cancelled(progress)?;
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            delete_path(&entry?.path(), progress)?;
+        }
+        return fs::remove_dir(path);
+    }
+    fs::remove_file(path)?;
+    let bytes = if metadata.is_symlink() {
+        0
+    } else {
+        metadata.len()
+    };
+    progress.add_done(1, bytes);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fe::journal::Journal;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    fn wait(queue: &mut JobQueue) -> JobResult {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(result) = queue.poll().pop() {
+                return result;
+            }
+            assert!(Instant::now() < deadline, "job didn't finish");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_copy_path() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("nested").join("b.txt"), "bb").unwrap();
+
+        let progress = JobProgress::default();
+        count(&src, &progress).unwrap();
+        assert_eq!(progress.files(), (0, 2));
+        assert_eq!(progress.bytes(), (0, 3));
+
+        let dest = dir.path().join("dest");
+        copy_path(&src, &dest, &progress).unwrap();
+
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "a");
+        assert_eq!(
+            fs::read_to_string(dest.join("nested").join("b.txt")).unwrap(),
+            "bb"
+        );
+        assert_eq!(progress.files(), (2, 2));
+        assert_eq!(progress.fraction(), 1.0);
+        // source is left untouched
+        assert!(src.join("nested").join("b.txt").exists());
+
+        // copying onto an existing entry fails instead of merging
+        assert!(copy_path(&src, &dest, &progress).is_err());
+    }
+
+    #[test]
+    fn test_cancelled_copy() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        fs::write(&src, "a").unwrap();
+
+        let progress = JobProgress::default();
+        progress.cancel();
+        let dest = dir.path().join("b.txt");
+        assert!(copy_path(&src, &dest, &progress).is_err());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_move_across_devices() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::SystemTime;
+
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        let file = src.join("a.sh");
+        fs::write(&file, "echo a").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o750)).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        std::os::unix::fs::symlink("a.sh", src.join("link")).unwrap();
+
+        let dest = dir.path().join("dest");
+        let progress = JobProgress::default();
+        move_across_devices(&src, &dest, &progress).unwrap();
+
+        assert!(!src.exists());
+        let moved = fs::metadata(dest.join("a.sh")).unwrap();
+        assert_eq!(moved.permissions().mode() & 0o777, 0o750);
+        assert_eq!(moved.modified().unwrap(), modified);
+        assert_eq!(
+            fs::read_link(dest.join("link")).unwrap(),
+            PathBuf::from("a.sh")
+        );
+        assert_eq!(progress.files(), (2, 2));
+
+        // an existing destination is never replaced, and the source is kept
+        let other = dir.path().join("other.txt");
+        fs::write(&other, "other").unwrap();
+        assert!(move_across_devices(&other, &dest, &progress).is_err());
+        assert!(other.exists());
+        assert!(dest.join("a.sh").exists());
+    }
+
+    #[test]
+    fn test_verify_copy() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("a.txt"), "abc").unwrap();
+        let dest = dir.path().join("dest");
+        copy_path(&src, &dest, &JobProgress::default()).unwrap();
+        assert!(verify_copy(&src, &dest).is_ok());
+
+        // same size, different contents
+        fs::write(dest.join("a.txt"), "abd").unwrap();
+        assert!(verify_copy(&src, &dest).is_err());
+
+        fs::write(dest.join("a.txt"), "ab").unwrap();
+        assert!(verify_copy(&src, &dest).is_err());
+    }
+
+    #[test]
+    fn test_undo_redo_move() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dest = dir.path().join("b.txt");
+        fs::write(&src, "a").unwrap();
+
+        let op = Operation::Move(vec![(src.clone(), dest.clone())]);
+        let progress = JobProgress::default();
+        run(&JobKind::Move(vec![(src.clone(), dest.clone())]), &progress).unwrap();
+        assert!(dest.exists());
+
+        run(&JobKind::Undo(op.clone()), &progress).unwrap();
+        assert!(src.exists());
+        assert!(!dest.exists());
+
+        run(&JobKind::Redo(op), &progress).unwrap();
+        assert!(!src.exists());
+        assert!(dest.exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_redo_link() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("link");
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        create_symlink(Path::new("a.txt"), &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a");
+
+        fs::remove_file(&path).unwrap();
+        let op = Operation::Link {
+            path: path.clone(),
+            target: PathBuf::from("a.txt"),
+        };
+        run(&JobKind::Redo(op), &JobProgress::default()).unwrap();
+        assert_eq!(fs::read_link(&path).unwrap(), PathBuf::from("a.txt"));
+    }
+
+    #[test]
+    fn test_failed_undo_keeps_op() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dest = dir.path().join("b.txt");
+        fs::write(&src, "a").unwrap();
+
+        let mut queue = JobQueue::new();
+        let mut journal = Journal::new(Vec::new(), Vec::new());
+        queue.spawn(JobKind::Move(vec![(src.clone(), dest.clone())]));
+        assert!(journal.finish_job(&wait(&mut queue)));
+        let op = Operation::Move(vec![(src.clone(), dest.clone())]);
+        assert_eq!(journal.undo_stack(), std::slice::from_ref(&op));
+
+        // the moved file is gone, so the undo fails and can be retried
+        fs::rename(&dest, dir.path().join("c.txt")).unwrap();
+        queue.spawn(JobKind::Undo(journal.pop_undo().unwrap()));
+        let result = wait(&mut queue);
+        assert!(matches!(result.status, JobStatus::Failed(_)));
+        assert!(journal.finish_job(&result));
+        assert_eq!(journal.undo_stack(), std::slice::from_ref(&op));
+        assert!(journal.redo_stack().is_empty());
+
+        fs::rename(dir.path().join("c.txt"), &dest).unwrap();
+        queue.spawn(JobKind::Undo(journal.pop_undo().unwrap()));
+        assert!(journal.finish_job(&wait(&mut queue)));
+        assert!(journal.undo_stack().is_empty());
+        assert_eq!(journal.redo_stack(), std::slice::from_ref(&op));
+
+        // same for a redo
+        fs::remove_file(&src).unwrap();
+        queue.spawn(JobKind::Redo(journal.pop_redo().unwrap()));
+        let result = wait(&mut queue);
+        assert!(matches!(result.status, JobStatus::Failed(_)));
+        assert!(journal.finish_job(&result));
+        assert_eq!(journal.redo_stack(), [op]);
+        assert!(journal.undo_stack().is_empty());
+    }
+
+    #[test]
+    fn test_job_queue() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dest = dir.path().join("b.txt");
+        fs::write(&src, "a").unwrap();
+
+        let mut queue = JobQueue::new();
+        let id = queue.spawn(JobKind::Move(vec![(src.clone(), dest.clone())]));
+        assert!(!queue.is_empty());
+
+        let result = wait(&mut queue);
+        assert_eq!(result.id, id);
+        assert_eq!(result.status, JobStatus::Done);
+        assert!(queue.is_empty());
+        assert!(!src.exists());
+        assert!(dest.exists());
+
+        queue.spawn(JobKind::Delete(vec![dest.clone()]));
+        assert_eq!(wait(&mut queue).status, JobStatus::Done);
+        assert!(!dest.exists());
+
+        queue.spawn(JobKind::Delete(vec![dest.clone()]));
+        assert!(matches!(wait(&mut queue).status, JobStatus::Failed(_)));
+    }
+}
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::jobs::{JobKind, JobResult, JobStatus};
+
+// Operation is a file operation done through fe, with enough
+// data to revert it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operation {
+    // an empty file or dir was created
+    Create {
+        #[serde(with = "path_bytes")]
+        path: PathBuf,
+        is_dir: bool,
+    },
+    // (origin, destination) pairs. Renames are moves within the same dir.
+    Move(#[serde(with = "path_bytes::pairs")] Vec<(PathBuf, PathBuf)>),
+    Copy(#[serde(with = "path_bytes::pairs")] Vec<(PathBuf, PathBuf)>),
+    Trash(#[serde(with = "path_bytes::list")] Vec<PathBuf>),
+    // a symlink to `target` was created at `path`
+    Link {
+        #[serde(with = "path_bytes")]
+        path: PathBuf,
+        #[serde(with = "path_bytes")]
+        target: PathBuf,
+    },
+}
+
+impl Operation {
+    // returns the operation done by a job, if it can be undone
+    pub fn from_job(kind: &JobKind) -> Option<Self> {
+        return match kind {
+            JobKind::Copy(pairs) => Some(Operation::Copy(pairs.clone())),
+            JobKind::Move(pairs) => Some(Operation::Move(pairs.clone())),
+            JobKind::Trash(paths) => Some(Operation::Trash(paths.clone())),
+            _ => None,
+        };
+    }
+
+    // returns the paths the operation acts on
+    pub fn paths(&self) -> Vec<&PathBuf> {
+        return match self {
+            Operation::Create { path, .. } | Operation::Link { path, .. } => vec![path],
+            Operation::Move(pairs) | Operation::Copy(pairs) => {
+                pairs.iter().map(|(from, _)| from).collect()
+            }
+            Operation::Trash(paths) => paths.iter().collect(),
+        };
+    }
+}
+
+// Journal keeps the operations that can be undone, and the undone
+// operations that can be redone.
+#[derive(Debug, Default)]
+pub struct Journal {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+}
+
+impl Journal {
+    const MAX_LEN: usize = 100;
+
+    pub fn new(undo: Vec<Operation>, redo: Vec<Operation>) -> Self {
+        Self { undo, redo }
+    }
+
+    // records an operation done by the user. Undone operations can't be
+    // redone after that.
+    pub fn record(&mut self, op: Operation) {
+        self.push_undo(op);
+        self.redo.clear();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Operation> {
+        return self.undo.pop();
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Operation> {
+        return self.redo.pop();
+    }
+
+    pub fn push_undo(&mut self, op: Operation) {
+        self.undo.push(op);
+        if self.undo.len() > Self::MAX_LEN {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn push_redo(&mut self, op: Operation) {
+        self.redo.push(op);
+        if self.redo.len() > Self::MAX_LEN {
+            self.redo.remove(0);
+        }
+    }
+
+    // records the operation done by a finished job. Undone operations can
+    // be redone and vice versa. An undo or redo that didn't finish is put
+    // back so it can be tried again, other failed jobs are dropped since
+    // they may have been partially applied. Returns whether the journal
+    // changed.
+    pub fn finish_job(&mut self, result: &JobResult) -> bool {
+        let done = result.status == JobStatus::Done;
+        match &result.kind {
+            JobKind::Undo(op) if done => self.push_redo(op.clone()),
+            JobKind::Undo(op) => self.push_undo(op.clone()),
+            JobKind::Redo(op) if done => self.push_undo(op.clone()),
+            JobKind::Redo(op) => self.push_redo(op.clone()),
+            kind => match Operation::from_job(kind) {
+                Some(op) if done => self.record(op),
+                _ => return false,
+            },
+        }
+        return true;
+    }
+
+    pub fn undo_stack(&self) -> &[Operation] {
+        return &self.undo;
+    }
+
+    pub fn redo_stack(&self) -> &[Operation] {
+        return &self.redo;
+    }
+}
+
+// serializes the paths of the operations as bytes, serde refuses paths
+// that aren't valid UTF-8. Bytes are written like strings, so journals
+// saved before still load.
+mod path_bytes {
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::{Deserialize, Serialize, Serializer};
+    use std::fmt;
+    use std::path::{Path, PathBuf};
+
+    use crate::utils::path::{path_from_bytes, path_to_bytes};
+
+    struct Bytes<'a>(&'a Path);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            return serializer.serialize_bytes(&path_to_bytes(self.0));
+        }
+    }
+
+    struct ByteBuf(PathBuf);
+
+    impl<'de> Deserialize<'de> for ByteBuf {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            return deserializer.deserialize_byte_buf(PathVisitor).map(ByteBuf);
+        }
+    }
+
+    struct PathVisitor;
+
+    impl Visitor<'_> for PathVisitor {
+        type Value = PathBuf;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            return formatter.write_str("the bytes of a path");
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<PathBuf, E> {
+            return Ok(path_from_bytes(bytes.to_vec()));
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<PathBuf, E> {
+            return Ok(path_from_bytes(bytes));
+        }
+
+        fn visit_str<E: de::Error>(self, path: &str) -> Result<PathBuf, E> {
+            return Ok(PathBuf::from(path));
+        }
+    }
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        return Bytes(path).serialize(serializer);
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        return ByteBuf::deserialize(deserializer).map(|path| path.0);
+    }
+
+    pub mod list {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            paths: &[PathBuf],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            return serializer.collect_seq(paths.iter().map(|path| Bytes(path)));
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<PathBuf>, D::Error> {
+            let paths = Vec::<ByteBuf>::deserialize(deserializer)?;
+            return Ok(paths.into_iter().map(|path| path.0).collect());
+        }
+    }
+
+    pub mod pairs {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            pairs: &[(PathBuf, PathBuf)],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            let pairs = pairs.iter().map(|(from, to)| (Bytes(from), Bytes(to)));
+            return serializer.collect_seq(pairs);
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<(PathBuf, PathBuf)>, D::Error> {
+            let pairs = Vec::<(ByteBuf, ByteBuf)>::deserialize(deserializer)?;
+            return Ok(pairs.into_iter().map(|(from, to)| (from.0, to.0)).collect());
+        }
+    }
+}
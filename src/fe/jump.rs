@@ -0,0 +1,123 @@
+use chrono::Utc;
+
+use super::diagnostic::Diagnostic;
+use super::FE;
+use crate::events::EventType;
+use crate::utils::frecency::{rank, Visit};
+
+// how many matches the jump dialog lists
+const MAX_RESULTS: usize = 20;
+
+// Jump is the state of the dialog to jump to a visited dir
+#[derive(Default)]
+pub struct Jump {
+    query: String,
+    visits: Vec<Visit>,
+    results: Vec<Visit>,
+    cursor: usize,
+}
+
+impl Jump {
+    fn update_results(&mut self) {
+        let terms: Vec<String> = self.query.split_whitespace().map(String::from).collect();
+        self.results = rank(self.visits.clone(), &terms, Utc::now().timestamp());
+        self.results.truncate(MAX_RESULTS);
+        self.cursor = 0;
+    }
+}
+
+impl FE {
+    // counts a visit to the active tab's dir, to jump back to it later
+    pub fn record_visit(&mut self) {
+        let path = self.tab().path.clone();
+        if let Err(err) = self.storage.record_visit(&path, Utc::now().timestamp()) {
+            self.diagnostics.push(Diagnostic::from_err(&err));
+        }
+    }
+
+    // opens the dialog to jump to a visited dir
+    pub fn open_jump(&mut self) {
+        // missing dirs are only dropped when picked, checking them all
+        // here would block on stale mounts
+        let visits = match self.storage.list_visits() {
+            Ok(visits) => visits,
+            Err(err) => {
+                self.diagnostics.push(Diagnostic::from_err(&err));
+                return;
+            }
+        };
+        let mut jump = Jump {
+            visits,
+            ..Default::default()
+        };
+        jump.update_results();
+        self.jump = Some(jump);
+    }
+
+    pub fn draw_jump(&mut self, ctx: &egui::Context) {
+        let jump = match &mut self.jump {
+            Some(jump) => jump,
+            None => return,
+        };
+
+        let mut open = true;
+        let mut picked = None;
+        egui::Window::new("Jump to dir")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let input = ui.text_edit_singleline(&mut jump.query);
+                input.request_focus();
+                if input.changed() {
+                    jump.update_results();
+                }
+
+                // the arrows move through the results while typing
+                ui.input_mut(|i| {
+                    if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+                        jump.cursor += 1;
+                    }
+                    if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                        jump.cursor = jump.cursor.saturating_sub(1);
+                    }
+                });
+                jump.cursor = jump.cursor.min(jump.results.len().saturating_sub(1));
+                if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    picked = jump.results.get(jump.cursor).map(|v| v.path.clone());
+                }
+
+                ui.separator();
+                if jump.results.is_empty() {
+                    ui.weak("No visited dir matches");
+                }
+                for (i, visit) in jump.results.iter().enumerate() {
+                    let label = ui.selectable_label(i == jump.cursor, visit.path.to_string_lossy());
+                    if label.clicked() {
+                        picked = Some(visit.path.clone());
+                    }
+                }
+            });
+
+        let escape = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        if let Some(path) = picked {
+            if path.is_dir() {
+                self.event_pool.emit_event(EventType::SetPath(path));
+                self.jump = None;
+                return;
+            }
+            // forget it and keep the dialog open to pick another one
+            if let Err(err) = self.storage.remove_visit(&path) {
+                self.diagnostics.push(Diagnostic::from_err(&err));
+            }
+            self.diagnostics.push(Diagnostic::default(format!(
+                "{} doesn't exist anymore",
+                path.to_string_lossy()
+            )));
+            jump.visits.retain(|visit| visit.path != path);
+            jump.update_results();
+        } else if !open || escape {
+            self.jump = None;
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use crate::utils::dir::{fs_to_fe_entry, FeEntry};
+
+// how many entries are sent to the table at once
+const BATCH_LEN: usize = 1000;
+// how many unreadable entries are reported one by one
+const MAX_REPORTED_ERRORS: usize = 5;
+
+// LoadMessage is what the loader sends while listing a dir
+#[derive(Debug)]
+pub enum LoadMessage {
+    Entries(Vec<FeEntry>),
+    // an entry, or the dir itself, couldn't be read
+    Error(String),
+}
+
+// DirLoader lists a dir on a worker thread, streaming its entries in
+// batches. Slow mounts don't block the UI, and dropping the loader stops
+// the listing.
+pub struct DirLoader {
+    receiver: Receiver<LoadMessage>,
+    cancelled: Arc<AtomicBool>,
+    // whether entries were received, the first ones replace the old
+    pub started: bool,
+    pub loaded: usize,
+    // the paths the watcher reloaded or removed meanwhile. What it saw is
+    // newer than the listing, whose copies of them are dropped.
+    pub changed: HashSet<PathBuf>,
+}
+
+impl DirLoader {
+    pub fn start(path: PathBuf) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let stop = cancelled.clone();
+        thread::spawn(move || {
+            let read_dir = match fs::read_dir(&path) {
+                Ok(read_dir) => read_dir,
+                Err(err) => {
+                    let message = format!("can't read {}: {}", path.to_string_lossy(), err);
+                    let _ = sender.send(LoadMessage::Error(message));
+                    return;
+                }
+            };
+
+            let mut batch = Vec::new();
+            let mut errors = 0;
+            for entry in read_dir {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let result = entry.and_then(|entry| {
+                    let name = entry.file_name();
+                    return fs_to_fe_entry(entry).map_err(|err| {
+                        std::io::Error::new(
+                            err.kind(),
+                            format!("{}: {}", name.to_string_lossy(), err),
+                        )
+                    });
+                });
+                match result {
+                    Ok(entry) => batch.push(entry),
+                    Err(err) => {
+                        errors += 1;
+                        if errors <= MAX_REPORTED_ERRORS {
+                            let _ = sender.send(LoadMessage::Error(format!("can't read {}", err)));
+                        }
+                    }
+                }
+                if batch.len() >= BATCH_LEN {
+                    let entries = std::mem::take(&mut batch);
+                    // nobody listens if the loader was dropped
+                    if sender.send(LoadMessage::Entries(entries)).is_err() {
+                        return;
+                    }
+                }
+            }
+            let _ = sender.send(LoadMessage::Entries(batch));
+            if errors > MAX_REPORTED_ERRORS {
+                let _ = sender.send(LoadMessage::Error(format!(
+                    "{} more entries of {} couldn't be read",
+                    errors - MAX_REPORTED_ERRORS,
+                    path.to_string_lossy()
+                )));
+            }
+        });
+
+        return Self {
+            receiver,
+            cancelled,
+            started: false,
+            loaded: 0,
+            changed: HashSet::new(),
+        };
+    }
+
+    // returns what was sent since the last call, and whether the listing
+    // still runs
+    pub fn poll(&self) -> (Vec<LoadMessage>, bool) {
+        let mut messages = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(TryRecvError::Empty) => return (messages, true),
+                Err(TryRecvError::Disconnected) => return (messages, false),
+            }
+        }
+    }
+}
+
+impl Drop for DirLoader {
+    // a stale load doesn't need to keep listing
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
@@ -3,39 +3,66 @@ use directories::UserDirs;
 use eframe::{self};
 use egui::{Align2, Response, Sense, Ui, Vec2};
 use std::fs;
-use std::path::{PathBuf, MAIN_SEPARATOR};
-use std::time::Instant;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use crate::commands::Commands;
 use crate::config::{parse_config, Config};
 use crate::events::{EventPool, EventType};
-use crate::utils::dir::{DirSorting, EntryKind, FeEntry, QuickAccessEntry, SortOrder};
+use crate::utils::dir::{EntryKind, EntryList, FeEntry, QuickAccessEntry};
+use crate::utils::watcher::Watcher;
 use crate::utils::{self, term};
 use crate::{cli, commands, storage};
 
-use self::draw::file::get_current_dir_context_menu;
+use self::batch_rename::BatchRename;
+use self::clipboard::{Clipboard, ClipboardMode};
+use self::grep::Grep;
+use self::index_search::{IndexSearch, Indexer, DEFAULT_REFRESH_MINUTES};
+use self::jobs::{JobKind, JobQueue, JobResult, JobStatus};
+use self::journal::Journal;
+use self::jump::Jump;
+use self::rename::RenameState;
+use self::selection::SelectMode;
+use self::tab::Tab;
+mod batch_rename;
+mod breadcrumbs;
+mod clipboard;
 mod diagnostic;
 mod draw;
 mod files;
+mod grep;
+mod history;
+mod index_search;
+pub mod jobs;
+pub mod journal;
+mod jump;
+mod loader;
+mod pane;
+mod query;
+mod rename;
+mod search;
+pub mod selection;
 mod style;
+mod tab;
+mod trash;
+mod type_ahead;
 
 pub struct FE {
-    // current dir state
-    path: std::path::PathBuf,
-    path_string: String,
-    entries: Vec<FeEntry>,
-    display_entries: Vec<FeEntry>,
-
-    prev_path: Option<std::path::PathBuf>,
-    dir_sorting: DirSorting,
+    // dirs being browsed, each with its own state
+    tabs: Vec<Tab>,
+    active_tab: usize,
+    // the tab header under the mouse, to drop entries on it
+    hovered_tab: Option<usize>,
+    // the tabs shown in the left and right panes when the view is split
+    split: Option<[usize; 2]>,
+    // the tab of the pane under the mouse while dragging entries
+    hovered_pane: Option<usize>,
 
     // data storage
     storage: storage::Storage,
     quick_access: Vec<QuickAccessEntry>,
 
-    // search state
-    search_txt: String,
-
     // ui events and shortcuts
     event_pool: EventPool,
 
@@ -52,21 +79,65 @@ pub struct FE {
     commands: commands::Commands,
     hovered_file: Option<FeEntry>,
 
-    dragging_file: Option<FeEntry>,
+    dragging_files: Vec<FeEntry>,
+
+    // entries copied or cut, waiting to be pasted
+    clipboard: Option<Clipboard>,
+    // the system clipboard, once something was copied
+    system_clipboard: Option<arboard::Clipboard>,
+
+    // file operations running in the background
+    jobs: JobQueue,
+    // a job waiting for the user to confirm it, and the question to ask
+    confirm_job: Option<(String, JobKind)>,
+
+    // operations that can be undone and redone
+    journal: Journal,
+
+    // the entry being renamed in the file table
+    renaming: Option<RenameState>,
+
+    // the entries being renamed with the batch rename dialog
+    batch_rename: Option<BatchRename>,
+
+    // the dialog to jump to a visited dir
+    jump: Option<Jump>,
+    // the panel searching file contents, when open
+    grep: Option<Grep>,
+
+    // where the db is, for the indexer to open its own connection
+    data_path: PathBuf,
+    // the refresh of the file index, while it runs
+    indexer: Option<Indexer>,
+    index_refresh: Duration,
+    next_index_check: Instant,
+    // whether every root is refreshed next, once the running refresh stops
+    force_index: bool,
+    // the dialog searching the file index
+    index_search: Option<IndexSearch>,
+    // reports the changes to the dirs of the tabs
+    watcher: Option<Watcher>,
 }
 
 impl FE {
     // creates FE from Config (usually parsed from a config file)
     pub fn from_config(config: Config) -> Self {
         let path = std::env::current_dir().unwrap();
-        let path_string = path.clone().to_str().unwrap().to_owned();
 
         let data_path: PathBuf = config.data_dir.expect("data_path is empty").into();
         println!("data_path: {:?}", data_path);
         fs::create_dir_all(data_path.parent().unwrap()).expect("cant create data dir");
-        let storage = storage::Storage::new(data_path).unwrap();
+        let storage = storage::Storage::new(data_path.clone()).unwrap();
 
         let quick_access_entries = storage.list_quick_access().unwrap();
+        let mut diagnostics = Vec::new();
+        let journal = storage.load_journal().unwrap_or_else(|err| {
+            diagnostics.push(Diagnostic::default(format!(
+                "can't load undo history: {}",
+                err
+            )));
+            Journal::default()
+        });
 
         let commands = match config.commands {
             None => Commands {
@@ -77,23 +148,41 @@ impl FE {
         };
 
         let mut fe = Self {
-            path,
-            path_string,
-            entries: Vec::new(),
-            display_entries: Vec::new(),
-            prev_path: None,
-            dir_sorting: DirSorting::FileNameAlphabetically(SortOrder::Asc),
-            storage: storage,
+            tabs: vec![Tab::new(path)],
+            active_tab: 0,
+            hovered_tab: None,
+            split: None,
+            hovered_pane: None,
+            storage,
             quick_access: quick_access_entries,
-            search_txt: "".to_owned(),
             event_pool: EventPool::new(),
             style: style::Style::default(),
             creating_file: false,
             new_file_name: "".to_owned(),
-            diagnostics: Vec::new(),
+            diagnostics,
             commands,
             hovered_file: None,
-            dragging_file: None,
+            dragging_files: Vec::new(),
+            clipboard: None,
+            system_clipboard: None,
+            jobs: JobQueue::new(),
+            confirm_job: None,
+            journal,
+            renaming: None,
+            batch_rename: None,
+            jump: None,
+            grep: None,
+            data_path,
+            indexer: None,
+            index_refresh: Duration::from_secs(
+                60 * config
+                    .index_refresh_minutes
+                    .unwrap_or(DEFAULT_REFRESH_MINUTES),
+            ),
+            next_index_check: Instant::now(),
+            force_index: false,
+            index_search: None,
+            watcher: None,
         };
 
         fe.load_dir_entries();
@@ -127,34 +216,45 @@ impl FE {
     }
 
     // init runs the initial style setup
-    fn init(self, cc: &eframe::CreationContext<'_>) -> Self {
+    fn init(mut self, cc: &eframe::CreationContext<'_>) -> Self {
         cc.egui_ctx.set_pixels_per_point(1.2);
+        // the watcher repaints to show the changes while fe is idle
+        let ctx = cc.egui_ctx.clone();
+        self.watcher = match Watcher::new(move || ctx.request_repaint()) {
+            Ok(watcher) => Some(watcher),
+            // changes are only seen on reload then
+            Err(err) if err.kind() == io::ErrorKind::Unsupported => None,
+            Err(err) => {
+                self.diagnostics.push(Diagnostic::from_err(&err));
+                None
+            }
+        };
         self
     }
 
-    fn update_display_entries(&mut self) {
-        self.display_entries = match self.search_txt.as_str() {
-            "" => self.entries.clone(),
-            _ => self
-                .entries
-                .iter()
-                .filter(|e| e.name.to_string_lossy().contains(&self.search_txt))
-                .cloned()
-                .collect(),
-        };
+    fn tab(&self) -> &Tab {
+        return &self.tabs[self.active_tab];
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        return &mut self.tabs[self.active_tab];
     }
 
-    fn handle_events(&mut self) -> Option<()> {
+    fn handle_events(&mut self, ctx: &egui::Context) -> Option<()> {
         for event in self.event_pool.get_events() {
             match event {
-                EventType::DirGoBack => {
-                    self.go_back_path();
+                EventType::DirGoBack(steps) => {
+                    self.go_back(steps);
+                }
+                EventType::DirGoForward(steps) => {
+                    self.go_forward(steps);
                 }
                 EventType::FavoriteCurrentPath => {
-                    if !files::is_favorited(&self.path, &self.quick_access) {
+                    let path = self.tab().path.clone();
+                    if !files::is_favorited(&path, &self.quick_access) {
                         let entry = QuickAccessEntry {
-                            name: self.path.file_name()?.to_os_string(),
-                            path: self.path.clone(),
+                            name: path.file_name()?.to_os_string(),
+                            path,
                         };
                         // update storage
                         if let Err(err) = self.storage.save_quick_access(&entry) {
@@ -163,10 +263,10 @@ impl FE {
                         // update memory
                         self.quick_access.push(entry);
                     } else {
-                        if let Err(err) = self.storage.remove_quick_access(&self.path) {
+                        if let Err(err) = self.storage.remove_quick_access(&path) {
                             self.diagnostics.push(Diagnostic::from_err(&err));
                         }
-                        self.quick_access.retain(|entry| entry.path != self.path);
+                        self.quick_access.retain(|entry| entry.path != path);
                     }
                 }
                 EventType::NewFile => {
@@ -176,31 +276,56 @@ impl FE {
                     self.set_path(path.clone());
                 }
                 EventType::OpenTerminal => {
-                    if let Some(err) = term::open_terminal(self.path_string.as_str()) {
+                    if let Some(err) = term::open_terminal(&self.tab().path) {
                         self.diagnostics.push(Diagnostic::from_err(&err));
                     };
                 }
-                EventType::DeleteFile(entry) => {
-                    self.delete_entry(entry);
-                    self.load_dir_entries();
+                EventType::DeleteFile(entries) => {
+                    let entries = self.target_entries(entries);
+                    if !entries.is_empty() {
+                        self.trash_entries(entries);
+                    }
+                }
+                EventType::CreateSymlink(entries) => {
+                    let entries = self.target_entries(entries);
+                    if !entries.is_empty() {
+                        self.create_symlinks(entries);
+                    }
+                }
+                EventType::DeletePermanently(entries) => {
+                    let entries = self.target_entries(entries);
+                    if !entries.is_empty() {
+                        self.confirm_delete(entries);
+                    }
+                }
+                EventType::OpenTrash => {
+                    self.open_trash();
+                }
+                EventType::RestoreTrashItem(item) => {
+                    self.restore_trash_item(item);
+                }
+                EventType::EmptyTrash(items) => {
+                    self.confirm_empty_trash(items);
                 }
                 EventType::Exec(path) => {
                     if let Err(err) = utils::run_exe(&path) {
                         self.diagnostics.push(Diagnostic::from_err(&err));
                     };
                 }
-                EventType::RunFileCmd(cmd, file_path) => {
-                    if let Err(err) = cmd.run(&file_path) {
-                        self.diagnostics.push(Diagnostic::from_err(&err.as_ref()));
-                    };
+                EventType::RunFileCmd(cmd, paths) => {
+                    for path in paths {
+                        if let Err(err) = cmd.run(&path) {
+                            self.diagnostics.push(Diagnostic::from_err(&err.as_ref()));
+                        };
+                    }
                 }
                 EventType::RunDirCmd(cmd) => {
-                    if let Err(err) = cmd.run(&self.path) {
+                    if let Err(err) = cmd.run(&self.tab().path) {
                         self.diagnostics.push(Diagnostic::from_err(&err.as_ref()));
                     };
                 }
                 EventType::ReloadDir => {
-                    self.set_path(self.path.clone());
+                    self.load_dir_entries();
                 }
                 EventType::MoveFile(3, files) => {
                     for file in files.iter() {
@@ -208,14 +333,13 @@ impl FE {
                             let file_name = path.file_name().unwrap_or_default();
                             // drop either in the current dir or the hovered dir
                             let dest_path = match self.hovered_file {
-                                None => self.path.join(file_name),
+                                None => self.tab().path.join(file_name),
                                 Some(ref entry) => match entry.entry_type {
                                     EntryKind::Dir(_) => entry.path.join(file_name),
-                                    EntryKind::File(_) => self.path.join(file_name),
+                                    EntryKind::File(_) => self.tab().path.join(file_name),
                                 },
                             };
                             self.move_file(path, &dest_path);
-                            self.load_dir_entries();
                         }
                     }
                 }
@@ -227,33 +351,330 @@ impl FE {
                     self.event_pool
                         .schedule_event(EventType::MoveFile(num + 1, files));
                 }
-                EventType::StartDragEntry(entry) => {
-                    self.dragging_file = Some(entry);
+                EventType::StartDragEntry(entries) => {
+                    self.dragging_files = self.target_entries(entries);
                 }
                 EventType::EndDragEntry => {
-                    if let Some(file) = &self.dragging_file {
-                        if let Some(dest) = &self.hovered_file {
-                            match dest.entry_type {
-                                EntryKind::Dir(_) => {
-                                    // Extract paths to avoid multiple mutable borrows
-                                    let file_path = file.path.clone();
-                                    let mut dest_path = dest.path.clone();
-                                    dest_path.push(file.name.clone());
-                                    self.move_file(&file_path, &dest_path);
-                                    self.load_dir_entries();
-                                }
-                                EntryKind::File(_) => (),
+                    let files = std::mem::take(&mut self.dragging_files);
+                    // entries are dropped on a tab header, on a dir, or on
+                    // the empty space of a pane
+                    let dest = match (self.hovered_tab, &self.hovered_file) {
+                        (Some(i), _) => Some(self.tabs[i].path.clone()),
+                        (None, Some(entry)) => match entry.entry_type {
+                            EntryKind::Dir(_) => Some(entry.path.clone()),
+                            EntryKind::File(_) => None,
+                        },
+                        (None, None) => self.hovered_pane.map(|i| self.tabs[i].path.clone()),
+                    };
+                    if let Some(dest) = dest {
+                        // dropping an entry on itself or on its own dir does nothing
+                        let pairs: Vec<(PathBuf, PathBuf)> = files
+                            .iter()
+                            .filter(|file| file.path != dest && file.path.parent() != Some(&dest))
+                            .map(|file| (file.path.clone(), dest.join(&file.name)))
+                            .collect();
+                        if !pairs.is_empty() {
+                            self.jobs.spawn(JobKind::Move(pairs));
+                        }
+                    }
+                }
+                EventType::Copy(entries) => {
+                    self.set_clipboard(entries, ClipboardMode::Copy);
+                }
+                EventType::Cut(entries) => {
+                    self.set_clipboard(entries, ClipboardMode::Cut);
+                }
+                EventType::Paste => {
+                    self.paste();
+                }
+                EventType::CancelJob(id) => {
+                    self.jobs.cancel(id);
+                }
+                EventType::JobFinished(result) => {
+                    self.journal_job(&result);
+                    match result.status {
+                        JobStatus::Done => (),
+                        JobStatus::Cancelled => self.diagnostics.push(Diagnostic::default(
+                            format!("{} cancelled", result.description),
+                        )),
+                        JobStatus::Failed(err) => self.diagnostics.push(Diagnostic::default(
+                            format!("{} failed: {}", result.description, err),
+                        )),
+                    }
+                    // the job may have changed any tab's dir
+                    self.reload_tabs();
+                }
+                EventType::StartRename(entries) => {
+                    let mut entries = self.target_entries(entries);
+                    if entries.len() > 1 {
+                        self.start_batch_rename(entries);
+                    } else if let Some(entry) = entries.pop() {
+                        self.start_rename(entry);
+                    }
+                }
+                EventType::BatchRename(entries) => {
+                    let entries = if !entries.is_empty() {
+                        entries
+                    } else if !self.tab().selection.is_empty() {
+                        self.tab().selected_entries()
+                    } else {
+                        self.tab().entries.clone()
+                    };
+                    let entries: Vec<FeEntry> =
+                        entries.into_iter().filter(|e| e.name != "..").collect();
+                    if !entries.is_empty() {
+                        self.start_batch_rename(entries);
+                    }
+                }
+                // the ".." row can't be selected
+                EventType::SelectEntry(entry, mode) if entry.name != ".." => {
+                    self.tab_mut().select(&entry, mode);
+                }
+                EventType::SelectAll => {
+                    self.tab_mut().select_all();
+                }
+                EventType::ClearSelection => {
+                    self.tab_mut().selection.clear();
+                }
+                EventType::MoveCursor(movement, mode) => {
+                    let page_len = self.page_len();
+                    let tab = self.tab_mut();
+                    let index = tab.move_cursor(movement, mode, page_len);
+                    if index.is_some() {
+                        tab.scroll_to_row = index;
+                        ctx.request_repaint();
+                    }
+                }
+                EventType::OpenCursor => {
+                    let tab = self.tab();
+                    if let Some(entry) = tab.selection.cursor(&tab.display_entries()) {
+                        match &entry.entry_type {
+                            EntryKind::Dir(_) => self
+                                .event_pool
+                                .schedule_event(EventType::SetPath(entry.path.clone())),
+                            EntryKind::File(file) => {
+                                // falls back to the default application
+                                let event = file
+                                    .is_clickable(&self.commands.file)
+                                    .unwrap_or_else(|| EventType::OpenDefault(entry.path.clone()));
+                                self.event_pool.schedule_event(event);
                             }
                         }
+                        ctx.request_repaint();
+                    }
+                }
+                EventType::TypeAhead(text) => {
+                    let now = Instant::now();
+                    let tab = self.tab_mut();
+                    tab.type_ahead.push(&text, now);
+                    let display = tab.display_entries();
+                    let found = tab.type_ahead.find(&display, now);
+                    if let Some(entry) = found.and_then(|index| display.get(index)).cloned() {
+                        tab.select(&entry, SelectMode::Replace);
+                        tab.scroll_to_row = found;
+                    }
+                    ctx.request_repaint();
+                }
+                EventType::NewTab => {
+                    self.new_tab();
+                }
+                EventType::CloseTab(index) => {
+                    self.close_tab(index.unwrap_or(self.active_tab));
+                }
+                EventType::SelectTab(index) => {
+                    self.select_tab(index);
+                }
+                EventType::NextTab => {
+                    self.select_tab((self.active_tab + 1) % self.tabs.len());
+                }
+                EventType::PrevTab => {
+                    self.select_tab((self.active_tab + self.tabs.len() - 1) % self.tabs.len());
+                }
+                EventType::ToggleSplit => {
+                    self.toggle_split();
+                }
+                EventType::SwitchPane => {
+                    if let Some(other) = self.other_pane_tab() {
+                        self.select_tab(other);
+                    }
+                }
+                EventType::CopyToOtherPane => {
+                    self.transfer_to_other_pane(ClipboardMode::Copy);
+                }
+                EventType::MoveToOtherPane => {
+                    self.transfer_to_other_pane(ClipboardMode::Cut);
+                }
+                EventType::RevealEntry(path) => {
+                    self.reveal(path);
+                }
+                EventType::OpenJump => {
+                    self.open_jump();
+                }
+                EventType::DirChanged(changes) => {
+                    self.apply_dir_changes(changes);
+                }
+                EventType::OpenIndexSearch => {
+                    self.open_index_search();
+                }
+                EventType::ToggleGrep => {
+                    self.toggle_grep();
+                }
+                EventType::OpenDefault(path) => {
+                    if let Err(err) = utils::open_default(&path) {
+                        self.diagnostics.push(Diagnostic::from_err(&err));
+                    };
+                }
+                EventType::GoToParent => {
+                    if let Some(parent) = self.tab().path.parent() {
+                        self.set_path(parent.to_path_buf());
                     }
-                    self.dragging_file = None;
                 }
+                EventType::RenameEntry(entry, name) => {
+                    self.rename_entry(entry, name);
+                }
+                EventType::CancelRename => {
+                    self.renaming = None;
+                }
+                EventType::Undo => match self.journal.pop_undo() {
+                    Some(op) => {
+                        self.save_journal();
+                        self.jobs.spawn(JobKind::Undo(op));
+                    }
+                    None => self
+                        .diagnostics
+                        .push(Diagnostic::default("nothing to undo".to_string())),
+                },
+                EventType::Redo => match self.journal.pop_redo() {
+                    Some(op) => {
+                        self.save_journal();
+                        self.jobs.spawn(JobKind::Redo(op));
+                    }
+                    None => self
+                        .diagnostics
+                        .push(Diagnostic::default("nothing to redo".to_string())),
+                },
                 _ => {}
             }
         }
         return Some(());
     }
 
+    // records the operation done by a finished job in the journal, see
+    // `Journal::finish_job`
+    fn journal_job(&mut self, result: &JobResult) {
+        if self.journal.finish_job(result) {
+            self.save_journal();
+        }
+    }
+
+    fn save_journal(&mut self) {
+        if let Err(err) = self.storage.save_journal(&self.journal) {
+            self.diagnostics.push(Diagnostic::from_err(&err));
+        }
+    }
+
+    // returns `entries`, or if it's empty the selection, or the hovered
+    // entry if nothing is selected. The ".." row points to the parent
+    // dir, so it's never a target.
+    fn target_entries(&self, entries: Vec<FeEntry>) -> Vec<FeEntry> {
+        let entries: Vec<FeEntry> = if !entries.is_empty() {
+            entries
+        } else if !self.tab().selection.is_empty() {
+            self.tab().selected_entries()
+        } else {
+            self.hovered_file.clone().into_iter().collect()
+        };
+        return entries.into_iter().filter(|e| e.name != "..").collect();
+    }
+
+    // fills the clipboard with the target entries and publishes
+    // them to the system clipboard.
+    fn set_clipboard(&mut self, entries: Vec<FeEntry>, mode: ClipboardMode) {
+        let entries = self.target_entries(entries);
+        if entries.is_empty() {
+            return;
+        }
+
+        let clipboard = Clipboard::new(entries, mode);
+        if let Err(err) = clipboard.publish(&mut self.system_clipboard) {
+            self.diagnostics.push(Diagnostic::from_err(&err));
+        }
+        self.clipboard = Some(clipboard);
+    }
+
+    fn draw_confirmation(&mut self, ctx: &egui::Context) {
+        let message = match &self.confirm_job {
+            Some((message, _)) => message.clone(),
+            None => return,
+        };
+
+        let mut confirmed = None;
+        egui::Window::new("confirm_window")
+            .title_bar(false)
+            .resizable(false)
+            .collapsible(false)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(message);
+                ui.horizontal(|ui| {
+                    if ui.button("Delete").clicked() {
+                        confirmed = Some(true);
+                    }
+                    if ui.button("Cancel").clicked()
+                        || ui.input(|i| i.key_pressed(egui::Key::Escape))
+                    {
+                        confirmed = Some(false);
+                    }
+                });
+            });
+
+        match confirmed {
+            Some(true) => {
+                if let Some((_, job)) = self.confirm_job.take() {
+                    self.jobs.spawn(job);
+                }
+            }
+            Some(false) => self.confirm_job = None,
+            None => (),
+        }
+    }
+
+    fn draw_jobs(&mut self, ctx: &egui::Context) {
+        if self.jobs.is_empty() {
+            return;
+        }
+
+        egui::TopBottomPanel::bottom("jobs_panel").show(ctx, |ui| {
+            ui.label("Jobs");
+            for job in self.jobs.jobs() {
+                ui.horizontal(|ui| {
+                    let (files_done, files_total) = job.progress.files();
+                    let (bytes_done, bytes_total) = job.progress.bytes();
+                    let text = format!(
+                        "{}/{} files, {} of {}",
+                        files_done,
+                        files_total,
+                        utils::human_readable_size(bytes_done),
+                        utils::human_readable_size(bytes_total),
+                    );
+
+                    ui.label(&job.status.message);
+                    ui.add(
+                        egui::ProgressBar::new(job.status.progress())
+                            .desired_width(240.0)
+                            .text(text),
+                    );
+                    if ui.button("Cancel").clicked() {
+                        self.event_pool.emit_event(EventType::CancelJob(job.id));
+                    }
+                });
+            }
+        });
+
+        // keep polling while jobs are running
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+
     fn draw_diagnostics(&mut self, ctx: &egui::Context) {
         self.diagnostics.retain(|d| d.expires_at > Instant::now());
 
@@ -286,7 +707,16 @@ impl eframe::App for FE {
                 .emit_event(EventType::MoveFile(0, dropped_files));
         }
 
+        // set by the file tables as they're drawn
+        self.hovered_file = None;
+
         self.event_pool.emit_input_events(ctx);
+        for result in self.jobs.poll() {
+            self.event_pool.emit_event(EventType::JobFinished(result));
+        }
+        self.refresh_index(ctx, false);
+        self.watch_dirs();
+        self.poll_loaders(ctx);
         // menu bar
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
@@ -298,92 +728,35 @@ impl eframe::App for FE {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
-            });
-        });
-
-        // path and search bars
-        egui::TopBottomPanel::top("top-bars").show(ctx, |ui| {
-            // path
-            ui.horizontal(|ui| {
-                ui.horizontal(|ui| {
-                    if ui.button("↩").clicked() {
-                        self.go_back_path();
-                    }
-                    if ui.button("⬆").clicked() {
-                        // go back 1 level
-                        match self.path.parent() {
-                            Some(parent) => {
-                                self.set_path(PathBuf::from(parent));
-                            }
-                            None => {
-                                self.diagnostics
-                                    .push(Diagnostic::default("no parent".to_string()));
-                            }
-                        }
-                    }
-
-                    ui.label("Path");
-
-                    let path_input = ui.text_edit_singleline(&mut self.path_string);
-
-                    // on 'enter' key press
-                    if path_input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                        // keep focus after enter
-                        path_input.request_focus();
-                        // change path
-                        self.event_pool
-                            .emit_event(EventType::SetPath(self.path_string.clone().into()));
-                        // append a '/' at the end of the path string
-                        if !self.path_string.ends_with(MAIN_SEPARATOR) {
-                            self.path_string.push(MAIN_SEPARATOR);
-
-                            // move cursor to last position
-                            if let Some(mut state) =
-                                egui::TextEdit::load_state(ui.ctx(), path_input.id)
-                            {
-                                let ccursor =
-                                    egui::text::CCursor::new(self.path_string.chars().count());
-                                state
-                                    .cursor
-                                    .set_char_range(Some(egui::text::CCursorRange::one(ccursor)));
-                                state.store(ui.ctx(), path_input.id);
-                                ui.ctx().memory_mut(|mem| mem.request_focus(path_input.id));
-                            }
-                        }
-                    }
-
-                    if self.event_pool.get_event(EventType::FocusPathBar) {
-                        path_input.request_focus();
-                    }
-
-                    if ui.button("Go").clicked() {
-                        // focus path input
-                        path_input.request_focus();
-                        self.event_pool
-                            .emit_event(EventType::SetPath(self.path_string.clone().into()));
-                    }
-
-                    let favorited = files::is_favorited(&self.path, &self.quick_access);
-                    if ui.button(if favorited { "🌟" } else { "⭐" }).clicked() {
-                        self.event_pool.emit_event(EventType::FavoriteCurrentPath);
-                    }
-                });
-
-                // search bar
-                ui.horizontal(|ui| {
-                    ui.separator();
-                    ui.label("Seach");
-                    let search_input = ui.text_edit_singleline(&mut self.search_txt);
-                    if self.event_pool.get_event(EventType::FocusSearchBar) {
-                        search_input.request_focus();
+                ui.menu_button("View", |ui| {
+                    let mut split = self.split.is_some();
+                    if ui.checkbox(&mut split, "Split view").clicked() {
+                        self.event_pool.emit_event(EventType::ToggleSplit);
+                        ui.close_menu();
                     }
-                    if search_input.changed() {
-                        self.update_display_entries();
+                    let mut grep = self.grep.is_some();
+                    if ui.checkbox(&mut grep, "Search in files").clicked() {
+                        self.event_pool.emit_event(EventType::ToggleGrep);
+                        ui.close_menu();
                     }
                 });
             });
         });
 
+        egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
+            self.draw_tabs(ui);
+        });
+
+        // path and search bars. Split panes have their own.
+        if self.split.is_none() {
+            egui::TopBottomPanel::top("top-bars").show(ctx, |ui| {
+                self.draw_path_bar(ui, true);
+            });
+        }
+
+        self.draw_jobs(ctx);
+        self.draw_grep(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // left part, pinned dirs
             egui::SidePanel::left("left_panel")
@@ -395,10 +768,8 @@ impl eframe::App for FE {
                         ui.separator();
                         // TODO: Home and Desktop should be stored to quick links on first load
                         // then, read from storage instead of hardcoding it here
-                        if ui.link("🏠 Home").clicked() {
-                            if home::home_dir().is_some() {
-                                self.set_path(home::home_dir().unwrap());
-                            }
+                        if ui.link("🏠 Home").clicked() && home::home_dir().is_some() {
+                            self.set_path(home::home_dir().unwrap());
                         };
                         if ui.link("📺 Desktop").clicked() {
                             if let Some(user_dirs) = UserDirs::new() {
@@ -409,14 +780,14 @@ impl eframe::App for FE {
                             }
                         }
 
+                        if ui.link("🗑 Trash").clicked() {
+                            self.event_pool.emit_event(EventType::OpenTrash);
+                        }
+
                         let quick_access = self.quick_access.clone();
                         for entry in quick_access {
                             if ui
-                                .link(format!(
-                                    "{} {}",
-                                    "📁",
-                                    entry.name.to_str().unwrap().to_owned(),
-                                ))
+                                .link(format!("{} {}", "📁", entry.name.to_string_lossy()))
                                 .clicked()
                             {
                                 self.set_path(entry.path);
@@ -427,11 +798,7 @@ impl eframe::App for FE {
 
             // right part, file list
             egui::CentralPanel::default().show_inside(ui, |ui| {
-                self.draw_files(ui);
-                // Create an invisible panel to handle the right-click
-                fill_remainder(ui).context_menu(|ui| {
-                    get_current_dir_context_menu(ui, &mut self.event_pool, &self.commands);
-                });
+                self.draw_panes(ui);
             });
         });
 
@@ -467,6 +834,10 @@ impl eframe::App for FE {
             });
         }
 
+        self.draw_confirmation(ctx);
+        self.draw_batch_rename(ctx);
+        self.draw_jump(ctx);
+        self.draw_index_search(ctx);
         self.draw_diagnostics(ctx);
 
         if ctx.input(|i| i.pointer.any_released()) {
@@ -481,7 +852,7 @@ impl eframe::App for FE {
         egui::Area::new(egui::Id::new("dragging_label_area"))
             .fixed_pos(egui::pos2(0.0, 0.0)) // This position can be adjusted as needed
             .show(ctx, |ui| {
-                if let Some(dragging) = &self.dragging_file {
+                if let Some(dragging) = self.dragging_files.first() {
                     // Get the current mouse position
                     if let Some(pos) = ctx.input(|i| i.pointer.hover_pos()) {
                         let draw_pos = egui::Pos2::new(pos.x + 5.0, pos.y - 5.0);
@@ -489,11 +860,14 @@ impl eframe::App for FE {
                         ui.painter().text(
                             draw_pos,
                             egui::Align2::LEFT_TOP,
-                            format!(
-                                "{} {}",
-                                dragging.get_icon(),
-                                dragging.name.clone().into_string().unwrap()
-                            ),
+                            match self.dragging_files.len() {
+                                1 => format!(
+                                    "{} {}",
+                                    dragging.get_icon(),
+                                    dragging.name.to_string_lossy()
+                                ),
+                                n => format!("{} {} items", dragging.get_icon(), n),
+                            },
                             egui::FontId {
                                 size: 16.0,
                                 family: ctx.style().text_styles[&egui::TextStyle::Body]
@@ -508,7 +882,7 @@ impl eframe::App for FE {
 
         // handle events after drawing everything, since
         // the drawing methods may emit events
-        self.handle_events();
+        self.handle_events(ctx);
         self.event_pool.flush_events();
     }
 }
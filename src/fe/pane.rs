@@ -0,0 +1,374 @@
+use std::path::{PathBuf, MAIN_SEPARATOR};
+
+use super::clipboard::ClipboardMode;
+use super::diagnostic::Diagnostic;
+use super::draw::file::get_current_dir_context_menu;
+use super::tab::Tab;
+use super::{files, fill_remainder, FE};
+use crate::events::EventType;
+use crate::utils::path::{common_prefix, complete_path, expand_path};
+
+// how many dirs the history menus list
+const HISTORY_MENU_LEN: usize = 15;
+
+impl FE {
+    // returns the tab shown in the pane that doesn't have focus
+    pub fn other_pane_tab(&self) -> Option<usize> {
+        let [left, right] = self.split?;
+        if self.active_tab == left {
+            return Some(right);
+        }
+        if self.active_tab == right {
+            return Some(left);
+        }
+        return None;
+    }
+
+    // splits the view in two panes, showing the current tab and the next
+    // one, or joins them back.
+    pub fn toggle_split(&mut self) {
+        if self.split.take().is_some() {
+            return;
+        }
+        let other = if self.tabs.len() > 1 {
+            (self.active_tab + 1) % self.tabs.len()
+        } else {
+            let mut tab = Tab::new(self.tab().path.clone());
+            tab.dir_sorting = self.tab().dir_sorting.clone();
+            tab.load_entries();
+            self.tabs.push(tab);
+            self.tabs.len() - 1
+        };
+        self.split = Some([self.active_tab, other]);
+    }
+
+    // copies or moves the target entries of the focused pane into the
+    // dir of the other pane.
+    pub fn transfer_to_other_pane(&mut self, mode: ClipboardMode) {
+        let other = match self.other_pane_tab() {
+            Some(other) => other,
+            None => {
+                self.diagnostics.push(Diagnostic::default(
+                    "split the view to copy or move between panes".to_string(),
+                ));
+                return;
+            }
+        };
+        let entries = self.target_entries(Vec::new());
+        if entries.is_empty() {
+            return;
+        }
+        let dir = self.tabs[other].path.clone();
+        let dir_entries = self.tabs[other].entries.clone();
+        self.transfer(entries, mode, &dir, dir_entries);
+    }
+
+    // draws the file tables, side by side when the view is split
+    pub fn draw_panes(&mut self, ui: &mut egui::Ui) {
+        self.hovered_pane = None;
+        let panes = match self.split {
+            Some(panes) => panes,
+            None => {
+                self.draw_pane(ui);
+                return;
+            }
+        };
+
+        let dragging = !self.dragging_files.is_empty();
+        ui.columns(2, |columns| {
+            for (column, tab_index) in columns.iter_mut().zip(panes) {
+                let focused = tab_index == self.active_tab;
+                let rect = column.max_rect();
+
+                // clicking a pane gives it focus, before anything in it
+                // handles the click.
+                let pressed = column.input(|i| {
+                    i.pointer.any_pressed()
+                        && i.pointer
+                            .interact_pos()
+                            .is_some_and(|pos| rect.contains(pos))
+                });
+                if pressed && !focused {
+                    self.event_pool.emit_event(EventType::SelectTab(tab_index));
+                }
+                let hovered = column.rect_contains_pointer(rect);
+                if dragging && hovered {
+                    self.hovered_pane = Some(tab_index);
+                }
+
+                let stroke = if focused {
+                    column.visuals().selection.stroke
+                } else {
+                    column.visuals().widgets.noninteractive.bg_stroke
+                };
+                egui::Frame::none()
+                    .stroke(stroke)
+                    .inner_margin(4.0)
+                    .show(column, |ui| {
+                        // draw the pane as if its tab was the active one
+                        let active_tab = self.active_tab;
+                        self.active_tab = tab_index;
+                        self.draw_path_bar(ui, focused);
+                        ui.separator();
+                        self.draw_pane(ui);
+                        self.active_tab = active_tab;
+                    });
+            }
+        });
+    }
+
+    // draws the active tab's dir, or the trash
+    fn draw_pane(&mut self, ui: &mut egui::Ui) {
+        if self.tab().trash_items.is_some() {
+            self.draw_trash(ui);
+            return;
+        }
+        if self.tab().recursive_search.is_some() {
+            self.draw_search_results(ui);
+            return;
+        }
+        if let Some(loader) = &self.tab().loader {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Loading... {} entries", loader.loaded));
+            });
+        }
+        self.draw_files(ui);
+        // Create an invisible panel to handle the right-click
+        let remainder = fill_remainder(ui);
+        if remainder.clicked() {
+            self.event_pool.emit_event(EventType::ClearSelection);
+        }
+        remainder.context_menu(|ui| {
+            get_current_dir_context_menu(ui, &mut self.event_pool, &self.commands);
+        });
+    }
+
+    // draws the path and search bars of the active tab. Only the focused
+    // pane reacts to the shortcuts focusing them.
+    pub fn draw_path_bar(&mut self, ui: &mut egui::Ui, focused: bool) {
+        // path
+        ui.horizontal(|ui| {
+            ui.horizontal(|ui| {
+                // right click lists the history to pick a dir from it
+                let back = ui.button("↩").on_hover_text("Back");
+                if back.clicked() {
+                    self.go_back(1);
+                }
+                back.context_menu(|ui| {
+                    let history = self.tab().history.back_list().take(HISTORY_MENU_LEN);
+                    if let Some(steps) = history_menu(ui, history) {
+                        self.event_pool.emit_event(EventType::DirGoBack(steps));
+                    }
+                });
+                let forward = ui.button("↪").on_hover_text("Forward");
+                if forward.clicked() {
+                    self.go_forward(1);
+                }
+                forward.context_menu(|ui| {
+                    let history = self.tab().history.forward_list().take(HISTORY_MENU_LEN);
+                    if let Some(steps) = history_menu(ui, history) {
+                        self.event_pool.emit_event(EventType::DirGoForward(steps));
+                    }
+                });
+                if ui.button("⬆").clicked() {
+                    // go back 1 level
+                    match self.tab().path.parent() {
+                        Some(parent) => {
+                            self.set_path(PathBuf::from(parent));
+                        }
+                        None => {
+                            self.diagnostics
+                                .push(Diagnostic::default("no parent".to_string()));
+                        }
+                    }
+                }
+
+                ui.label("Path");
+
+                if focused && self.event_pool.get_event(EventType::FocusPathBar) {
+                    self.tab_mut().editing_path = true;
+                }
+                if self.tab().editing_path {
+                    self.draw_path_input(ui, focused);
+                } else {
+                    self.draw_breadcrumbs(ui);
+                }
+
+                let tab = &self.tabs[self.active_tab];
+                let favorited = files::is_favorited(&tab.path, &self.quick_access);
+                if ui.button(if favorited { "🌟" } else { "⭐" }).clicked() {
+                    self.event_pool.emit_event(EventType::FavoriteCurrentPath);
+                }
+            });
+
+            // search bar
+            ui.horizontal(|ui| {
+                ui.separator();
+                ui.label("Seach");
+                let tab = &mut self.tabs[self.active_tab];
+                let search_input = ui.text_edit_singleline(&mut tab.search_txt);
+                if focused && self.event_pool.get_event(EventType::FocusSearchBar) {
+                    search_input.request_focus();
+                }
+                let mut changed = search_input.changed();
+                changed |= ui
+                    .checkbox(&mut tab.recursive, "Recursive")
+                    .on_hover_text("Search in subdirs too")
+                    .changed();
+                ui.add_enabled_ui(tab.recursive, |ui| {
+                    ui.menu_button("⚙", |ui| {
+                        let options = &mut tab.search_options;
+                        ui.horizontal(|ui| {
+                            ui.label("Max depth");
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut options.max_depth)
+                                        .clamp_range(1..=64),
+                                )
+                                .changed();
+                        });
+                        changed |= ui
+                            .checkbox(&mut options.skip_hidden, "Skip hidden files")
+                            .changed();
+                        changed |= ui
+                            .checkbox(&mut options.respect_gitignore, "Skip .gitignored files")
+                            .changed();
+                    });
+                });
+                if changed {
+                    tab.update_search();
+                }
+                if let Some(err) = &tab.search_error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+            });
+        });
+    }
+
+    // draws the editable path of the active tab, with the dirs completing
+    // it in a dropdown. Tab completes the path as far as possible.
+    fn draw_path_input(&mut self, ui: &mut egui::Ui, focused: bool) {
+        let tab = &mut self.tabs[self.active_tab];
+        // keep focus on tab to complete instead
+        let path_input = ui.add(egui::TextEdit::singleline(&mut tab.path_string).lock_focus(true));
+        if path_input.changed() || path_input.gained_focus() {
+            tab.path_completions = complete_path(&tab.path_string, &tab.path);
+        }
+
+        let tab_pressed = path_input.has_focus()
+            && ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Tab));
+        if tab_pressed {
+            let completion = match tab.path_completions.as_slice() {
+                [completion] => completion.clone(),
+                completions => common_prefix(completions),
+            };
+            if completion.len() > tab.path_string.len() {
+                tab.path_string = completion;
+                tab.path_completions = complete_path(&tab.path_string, &tab.path);
+                move_cursor_to_end(ui, &path_input, &tab.path_string);
+            }
+        }
+
+        // dropdown with the completions
+        let popup_id = path_input.id.with("completions");
+        if path_input.has_focus() {
+            if tab.path_completions.is_empty() {
+                ui.memory_mut(|mem| mem.close_popup());
+            } else {
+                ui.memory_mut(|mem| mem.open_popup(popup_id));
+            }
+        }
+        let mut completion_hovered = false;
+        let mut picked = None;
+        egui::popup_below_widget(ui, popup_id, &path_input, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for completion in &tab.path_completions {
+                        if ui.selectable_label(false, completion).clicked() {
+                            picked = Some(completion.clone());
+                        }
+                    }
+                });
+            completion_hovered = ui.rect_contains_pointer(ui.min_rect());
+        });
+
+        // on 'enter' key press
+        let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+        let mut submit = path_input.lost_focus() && enter;
+        if let Some(completion) = picked {
+            tab.path_string = completion;
+            submit = true;
+        }
+
+        if focused && self.event_pool.get_event(EventType::FocusPathBar) {
+            path_input.request_focus();
+        }
+
+        let go = ui.button("Go");
+        if go.clicked() {
+            submit = true;
+        }
+
+        // leaving the input shows the breadcrumbs again, unless
+        // it's to click Go or a completion
+        if path_input.lost_focus() && !enter && !go.hovered() && !completion_hovered {
+            tab.editing_path = false;
+            tab.path_string = tab.path.to_string_lossy().to_string();
+            ui.memory_mut(|mem| mem.close_popup());
+        }
+
+        if !submit {
+            return;
+        }
+        // keep focus to type the next path
+        path_input.request_focus();
+        match expand_path(&tab.path_string, &tab.path) {
+            Ok(path) => {
+                // append a '/' at the end of the path string
+                tab.path_string = path.to_string_lossy().to_string();
+                if !tab.path_string.ends_with(MAIN_SEPARATOR) {
+                    tab.path_string.push(MAIN_SEPARATOR);
+                }
+                move_cursor_to_end(ui, &path_input, &tab.path_string);
+                tab.path_completions = complete_path(&tab.path_string, &path);
+                // change path
+                self.event_pool.emit_event(EventType::SetPath(path));
+            }
+            Err(err) => self.diagnostics.push(Diagnostic::default(err)),
+        }
+    }
+}
+
+// moves the cursor of the text input after the last character
+fn move_cursor_to_end(ui: &egui::Ui, input: &egui::Response, text: &str) {
+    if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), input.id) {
+        let ccursor = egui::text::CCursor::new(text.chars().count());
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(ccursor)));
+        state.store(ui.ctx(), input.id);
+    }
+}
+
+// lists the dirs of the history, returning how many steps away the
+// clicked one is.
+fn history_menu<'a>(
+    ui: &mut egui::Ui,
+    history: impl Iterator<Item = &'a PathBuf>,
+) -> Option<usize> {
+    let mut clicked = None;
+    let mut empty = true;
+    for (i, path) in history.enumerate() {
+        empty = false;
+        if ui.button(path.to_string_lossy()).clicked() {
+            clicked = Some(i + 1);
+            ui.close_menu();
+        }
+    }
+    if empty {
+        ui.label("No history");
+    }
+    return clicked;
+}
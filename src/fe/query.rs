@@ -0,0 +1,339 @@
+use std::cmp::Ordering;
+use std::time::{Duration, SystemTime};
+
+use regex::Regex;
+
+use crate::utils::dir::{EntryKind, FeEntry};
+use crate::utils::fuzzy::fuzzy_match;
+use crate::utils::glob::glob_match;
+
+// Term is a part of a search query
+#[derive(Debug, Clone)]
+enum Term {
+    // matched fuzzily against the name
+    Fuzzy(String),
+    // `*.rs`, matched against the whole name
+    Glob(String),
+    // `/regex/`
+    Regex(Regex),
+    // `size:>10M`
+    Size(Ordering, u64),
+    // `modified:<7d`, compares how long ago the entry was modified
+    Modified(Ordering, Duration),
+    // `type:dir` or `type:file`
+    Type(bool),
+    // `ext:png,jpg`
+    Ext(Vec<String>),
+}
+
+// Query filters the entries of the search bar
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+// QueryMatch is how well an entry matches, and which chars of its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryMatch {
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+impl Query {
+    // parses the search bar text. Words are matched fuzzily against the
+    // names, other terms are globs, `/regexes/` and `key:value` filters.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut terms = Vec::new();
+        for word in split_words(text)? {
+            terms.push(parse_term(&word)?);
+        }
+        return Ok(Self { terms });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.terms.is_empty();
+    }
+
+    // whether the matches should be sorted by score
+    pub fn is_ranked(&self) -> bool {
+        return self.terms.iter().any(|t| matches!(t, Term::Fuzzy(_)));
+    }
+
+    // returns how the entry matches, if it matches every term
+    pub fn matches(&self, entry: &FeEntry, now: SystemTime) -> Option<QueryMatch> {
+        let name = entry.name.to_string_lossy();
+        let mut result = QueryMatch {
+            score: 0,
+            positions: Vec::new(),
+        };
+        for term in &self.terms {
+            match term {
+                Term::Fuzzy(pattern) => {
+                    let m = fuzzy_match(pattern, &name)?;
+                    result.score += m.score;
+                    result.positions.extend(m.positions);
+                }
+                Term::Glob(pattern) => {
+                    if !glob_match(pattern, &name.to_lowercase()) {
+                        return None;
+                    }
+                }
+                Term::Regex(regex) => {
+                    let m = regex.find(&name)?;
+                    let start = name[..m.start()].chars().count();
+                    let len = m.as_str().chars().count();
+                    result.positions.extend(start..start + len);
+                }
+                Term::Size(ordering, size) => match &entry.entry_type {
+                    EntryKind::File(file) if file.size.cmp(size) == *ordering => {}
+                    _ => return None,
+                },
+                Term::Modified(ordering, age) => match &entry.entry_type {
+                    EntryKind::File(file) => {
+                        let modified_ago = now.duration_since(file.modified).unwrap_or_default();
+                        if modified_ago.cmp(age) != *ordering {
+                            return None;
+                        }
+                    }
+                    _ => return None,
+                },
+                Term::Type(is_dir) => {
+                    if matches!(entry.entry_type, EntryKind::Dir(_)) != *is_dir {
+                        return None;
+                    }
+                }
+                Term::Ext(exts) => match &entry.entry_type {
+                    EntryKind::File(file) => {
+                        let ext = file.ext().unwrap_or("").to_lowercase();
+                        if !exts.contains(&ext) {
+                            return None;
+                        }
+                    }
+                    _ => return None,
+                },
+            }
+        }
+        result.positions.sort();
+        result.positions.dedup();
+        return Some(result);
+    }
+}
+
+// splits the text on whitespace, keeping `/regexes/` whole
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = text.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut word = c.to_string();
+        if c == '/' {
+            let mut escaped = false;
+            let mut closed = false;
+            for c in chars.by_ref() {
+                word.push(c);
+                if c == '/' && !escaped {
+                    closed = true;
+                    break;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            if !closed {
+                return Err(format!("missing closing '/' in {}", word));
+            }
+            words.push(word);
+            continue;
+        }
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            word.push(c);
+        }
+        words.push(word);
+    }
+    return Ok(words);
+}
+
+fn parse_term(word: &str) -> Result<Term, String> {
+    if let Some(pattern) = word.strip_prefix('/').and_then(|w| w.strip_suffix('/')) {
+        // `\/` is how a '/' is written in the pattern
+        return Regex::new(&pattern.replace("\\/", "/"))
+            .map(Term::Regex)
+            .map_err(|err| format!("invalid regex: {}", err));
+    }
+    if let Some((key, value)) = word.split_once(':') {
+        match key {
+            "size" => {
+                let (ordering, value) = parse_comparison(value);
+                return Ok(Term::Size(ordering, parse_size(value)?));
+            }
+            "modified" => {
+                let (ordering, value) = parse_comparison(value);
+                return Ok(Term::Modified(ordering, parse_age(value)?));
+            }
+            "type" => {
+                return match value {
+                    "dir" | "d" => Ok(Term::Type(true)),
+                    "file" | "f" => Ok(Term::Type(false)),
+                    _ => Err(format!("invalid type '{}', use dir or file", value)),
+                };
+            }
+            "ext" => {
+                let exts: Vec<String> = value
+                    .split(',')
+                    .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                    .filter(|ext| !ext.is_empty())
+                    .collect();
+                if exts.is_empty() {
+                    return Err("ext: needs at least one extension".to_string());
+                }
+                return Ok(Term::Ext(exts));
+            }
+            // names can have a ':' too
+            _ => {}
+        }
+    }
+    if word.contains(['*', '?', '[']) {
+        return Ok(Term::Glob(word.to_lowercase()));
+    }
+    return Ok(Term::Fuzzy(word.to_string()));
+}
+
+// splits the leading `<` or `>` of the value. The value has to be
+// equal without one.
+fn parse_comparison(value: &str) -> (Ordering, &str) {
+    if let Some(value) = value.strip_prefix('>') {
+        return (Ordering::Greater, value);
+    }
+    if let Some(value) = value.strip_prefix('<') {
+        return (Ordering::Less, value);
+    }
+    return (Ordering::Equal, value.strip_prefix('=').unwrap_or(value));
+}
+
+// parses a size like `10M` or `1.5GB`, in bytes
+fn parse_size(value: &str) -> Result<u64, String> {
+    let upper = value.to_uppercase();
+    let number = upper.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = &upper[number.len()..];
+    let multiplier: u64 = match unit.trim_end_matches('B') {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        "T" => 1024 * 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid size unit '{}', use K, M, G or T", unit)),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+    return Ok((number * multiplier as f64) as u64);
+}
+
+// parses an age like `30m` or `7d`
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = &value[number.len()..];
+    let seconds: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "invalid age unit '{}', use s, m, h, d, w or y",
+                unit
+            ))
+        }
+    };
+    let invalid = || format!("invalid age '{}'", value);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let seconds = number.checked_mul(seconds).ok_or_else(invalid)?;
+    return Ok(Duration::from_secs(seconds));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::dir::{Dir, File};
+    use std::path::PathBuf;
+
+    fn file(name: &str, size: u64, age: u64, now: SystemTime) -> FeEntry {
+        let path = PathBuf::from("/tmp").join(name);
+        return FeEntry {
+            name: name.into(),
+            path: path.clone(),
+            entry_type: EntryKind::File(File {
+                path,
+                is_exe: false,
+                size,
+                modified: now - Duration::from_secs(age),
+                link: None,
+            }),
+        };
+    }
+
+    fn matching<'a>(query: &str, entries: &'a [FeEntry], now: SystemTime) -> Vec<&'a str> {
+        let query = Query::parse(query).unwrap();
+        return entries
+            .iter()
+            .filter(|e| query.matches(e, now).is_some())
+            .map(|e| e.name.to_str().unwrap())
+            .collect();
+    }
+
+    #[test]
+    fn test_query() {
+        let now = SystemTime::now();
+        let day = 24 * 60 * 60;
+        let entries = vec![
+            file("config.toml", 100, 10 * day, now),
+            file("main.rs", 20 * 1024 * 1024, day, now),
+            file("photo.PNG", 5 * 1024 * 1024, 30 * day, now),
+            FeEntry {
+                name: "src".into(),
+                path: PathBuf::from("/tmp/src"),
+                entry_type: EntryKind::Dir(Dir { link: None }),
+            },
+        ];
+
+        assert_eq!(matching("cfgtoml", &entries, now), vec!["config.toml"]);
+        assert_eq!(matching("*.rs", &entries, now), vec!["main.rs"]);
+        assert_eq!(
+            matching("/^[a-z]+\\.(rs|png)$/", &entries, now),
+            vec!["main.rs"]
+        );
+        assert_eq!(matching("size:>10M", &entries, now), vec!["main.rs"]);
+        assert_eq!(
+            matching("size:<10m", &entries, now),
+            vec!["config.toml", "photo.PNG"]
+        );
+        assert_eq!(matching("modified:<7d", &entries, now), vec!["main.rs"]);
+        assert_eq!(matching("type:dir", &entries, now), vec!["src"]);
+        assert_eq!(matching("ext:png,jpg", &entries, now), vec!["photo.PNG"]);
+        assert_eq!(
+            matching("ext:toml,rs size:<1k", &entries, now),
+            vec!["config.toml"]
+        );
+        assert_eq!(matching("", &entries, now).len(), 4);
+
+        let query = Query::parse("/a.n/").unwrap();
+        let m = query.matches(&entries[1], now).unwrap();
+        assert_eq!(m.positions, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        assert!(Query::parse("/[/").is_err());
+        assert!(Query::parse("/unclosed").is_err());
+        assert!(Query::parse("size:>10X").is_err());
+        assert!(Query::parse("size:big").is_err());
+        assert!(Query::parse("modified:<7").is_err());
+        assert!(Query::parse("modified:<1000000000000y").is_err());
+        assert!(Query::parse("type:link").is_err());
+        assert!(Query::parse("ext:").is_err());
+        // unknown keys are part of the name
+        assert!(Query::parse("note:todo").is_ok());
+    }
+}
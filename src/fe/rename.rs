@@ -0,0 +1,66 @@
+use std::ffi::OsString;
+use std::path::Path;
+
+use super::diagnostic::Diagnostic;
+use super::FE;
+use crate::utils::dir::{validate_new_name, EntryKind, FeEntry};
+
+// RenameState is the entry being renamed inline in the file table.
+pub struct RenameState {
+    pub entry: FeEntry,
+    pub name: String,
+    // whether the name without extension should be selected, which
+    // happens when the input is first shown.
+    pub select_stem: bool,
+}
+
+impl RenameState {
+    pub fn new(entry: FeEntry) -> Self {
+        Self {
+            name: entry.name.to_string_lossy().to_string(),
+            entry,
+            select_stem: true,
+        }
+    }
+
+    // returns the length in chars of the name without its extension
+    pub fn stem_len(&self) -> usize {
+        if let EntryKind::Dir(_) = self.entry.entry_type {
+            return self.name.chars().count();
+        }
+        return Path::new(&self.name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().chars().count())
+            .unwrap_or(0);
+    }
+}
+
+impl FE {
+    pub fn start_rename(&mut self, entry: FeEntry) {
+        self.renaming = Some(RenameState::new(entry));
+    }
+
+    // renames the entry if `name` is valid, otherwise keeps
+    // editing and reports why.
+    pub fn rename_entry(&mut self, entry: FeEntry, name: String) {
+        // the input shows names that aren't valid UTF-8 lossily, left
+        // as is they keep their bytes
+        if name == entry.name.to_string_lossy() {
+            self.renaming = None;
+            return;
+        }
+        let name = OsString::from(name);
+
+        if let Err(message) = validate_new_name(&name, &entry, &self.tab().entries) {
+            self.diagnostics.push(Diagnostic::default(message));
+            if let Some(state) = &mut self.renaming {
+                state.select_stem = true;
+            }
+            return;
+        }
+
+        let dest = entry.path.with_file_name(&name);
+        self.move_file(&entry.path, &dest);
+        self.renaming = None;
+    }
+}
@@ -0,0 +1,418 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+
+use egui_extras::{Column, TableBuilder};
+
+use super::draw::file::highlighted_name;
+use super::query::Query;
+use super::FE;
+use crate::events::EventType;
+use crate::utils::dir::{fs_to_fe_entry, FeEntry};
+use crate::utils::gitignore::{self, Gitignore};
+
+// the search stops after finding that many entries
+const MAX_RESULTS: usize = 10_000;
+
+// SearchOptions limit what the recursive search walks into
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchOptions {
+    // how many dirs deep to look, 1 being the current dir only
+    pub max_depth: usize,
+    pub skip_hidden: bool,
+    pub respect_gitignore: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 10,
+            skip_hidden: true,
+            respect_gitignore: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub entry: FeEntry,
+    // the dir of the entry, relative to where the search started
+    pub location: String,
+    // the chars of the name matching the query
+    pub positions: Vec<usize>,
+}
+
+// RecursiveSearch looks for entries under a dir on a worker thread. The
+// results are streamed back while it walks the tree.
+pub struct RecursiveSearch {
+    pub results: Vec<SearchResult>,
+    pub running: bool,
+    receiver: Receiver<Vec<SearchResult>>,
+    cancelled: Arc<AtomicBool>,
+    dirs_scanned: Arc<AtomicU64>,
+}
+
+impl RecursiveSearch {
+    pub fn start(root: PathBuf, query: Query, options: SearchOptions) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let dirs_scanned = Arc::new(AtomicU64::new(0));
+
+        let walker = TreeWalker {
+            options,
+            cancelled: cancelled.clone(),
+            dirs_scanned: dirs_scanned.clone(),
+        };
+        thread::spawn(move || {
+            let mut found = 0;
+            walker.walk(&root, &mut |entries| {
+                let now = SystemTime::now();
+                let mut results = Vec::new();
+                for entry in entries {
+                    let fe_entry = match fs_to_fe_entry(entry) {
+                        Ok(fe_entry) => fe_entry,
+                        Err(_) => continue,
+                    };
+                    if let Some(m) = query.matches(&fe_entry, now) {
+                        results.push(SearchResult {
+                            location: location(&root, &fe_entry.path),
+                            entry: fe_entry,
+                            positions: m.positions,
+                        });
+                    }
+                }
+                if results.is_empty() {
+                    return true;
+                }
+                results.truncate(MAX_RESULTS - found);
+                found += results.len();
+                // the search was dropped if nobody listens anymore
+                return sender.send(results).is_ok() && found < MAX_RESULTS;
+            });
+        });
+
+        return Self {
+            results: Vec::new(),
+            running: true,
+            receiver,
+            cancelled,
+            dirs_scanned,
+        };
+    }
+
+    // collects the results found since the last call
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(results) => self.results.extend(results),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.running = false;
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn dirs_scanned(&self) -> u64 {
+        return self.dirs_scanned.load(Ordering::Relaxed);
+    }
+}
+
+impl Drop for RecursiveSearch {
+    // a replaced search doesn't need to keep walking
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// TreeWalker walks the dirs under a root depth first, leaving out what
+// the options skip. It runs on the worker thread of a search.
+pub struct TreeWalker {
+    pub options: SearchOptions,
+    pub cancelled: Arc<AtomicBool>,
+    pub dirs_scanned: Arc<AtomicU64>,
+}
+
+impl TreeWalker {
+    // calls `visit` with the entries of each dir, until it returns false
+    // or the walk is cancelled
+    pub fn walk(&self, root: &Path, visit: &mut dyn FnMut(Vec<fs::DirEntry>) -> bool) {
+        let mut gitignores = match self.options.respect_gitignore {
+            // the .gitignore of the root itself is read while walking
+            true => gitignore::parents(root),
+            false => Vec::new(),
+        };
+        self.walk_dir(root, 1, &mut gitignores, visit);
+    }
+
+    // visits the dir, then its subdirs. Returns false once the walk
+    // should stop.
+    fn walk_dir(
+        &self,
+        dir: &Path,
+        depth: usize,
+        gitignores: &mut Vec<Gitignore>,
+        visit: &mut dyn FnMut(Vec<fs::DirEntry>) -> bool,
+    ) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+        let read_dir = match fs::read_dir(dir) {
+            Ok(read_dir) => read_dir,
+            // unreadable dirs are skipped
+            Err(_) => return true,
+        };
+        self.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+
+        let respect_gitignore = self.options.respect_gitignore;
+        // the rules of the outer repository don't apply in a nested one
+        let mut nested = Vec::new();
+        let gitignores = match respect_gitignore && depth > 1 && gitignore::is_repo_root(dir) {
+            true => &mut nested,
+            false => gitignores,
+        };
+        let own_gitignore = respect_gitignore && push_gitignore(dir, gitignores);
+
+        let mut entries = Vec::new();
+        let mut subdirs = Vec::new();
+        for entry in read_dir.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            if self.options.skip_hidden && name.starts_with('.') {
+                continue;
+            }
+            if respect_gitignore
+                && (name == ".git" || gitignore::is_ignored(gitignores, &entry.path(), is_dir))
+            {
+                continue;
+            }
+            if is_dir {
+                subdirs.push(entry.path());
+            }
+            entries.push(entry);
+        }
+
+        let mut keep_going = visit(entries);
+        if keep_going && depth < self.options.max_depth {
+            subdirs.sort();
+            for subdir in subdirs {
+                if !self.walk_dir(&subdir, depth + 1, gitignores, visit) {
+                    keep_going = false;
+                    break;
+                }
+            }
+        }
+
+        if own_gitignore {
+            gitignores.pop();
+        }
+        return keep_going;
+    }
+}
+
+// adds the .gitignore of the dir to the stack. Returns whether it had one.
+fn push_gitignore(dir: &Path, gitignores: &mut Vec<Gitignore>) -> bool {
+    return match Gitignore::from_dir(dir) {
+        Some(gitignore) => {
+            gitignores.push(gitignore);
+            true
+        }
+        None => false,
+    };
+}
+
+// returns the dir of `path` relative to `root`
+pub fn location(root: &Path, path: &Path) -> String {
+    let parent = path.parent().unwrap_or(path);
+    return match parent.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.to_string_lossy().to_string(),
+        Err(_) => parent.to_string_lossy().to_string(),
+    };
+}
+
+impl FE {
+    // opens the dir of the entry, with the entry selected
+    pub fn reveal(&mut self, path: PathBuf) {
+        let parent = match path.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => return,
+        };
+        self.set_path(parent.clone());
+
+        // the entry is selected once the dir is loaded
+        let tab = self.tab_mut();
+        if tab.path == parent {
+            tab.pending_reveal = Some(path);
+        }
+    }
+
+    // draws the results of the active tab's recursive search
+    pub fn draw_search_results(&mut self, ui: &mut egui::Ui) {
+        let search = match &mut self.tabs[self.active_tab].recursive_search {
+            Some(search) => search,
+            None => return,
+        };
+        search.poll();
+
+        ui.horizontal(|ui| {
+            if search.running {
+                ui.spinner();
+                ui.label(format!(
+                    "Searching... {} dirs, {} found",
+                    search.dirs_scanned(),
+                    search.results.len()
+                ));
+                if ui.button("Cancel").clicked() {
+                    search.cancel();
+                }
+            } else if search.results.len() >= MAX_RESULTS {
+                ui.label(format!("Showing the first {} results", MAX_RESULTS));
+            } else {
+                ui.label(format!("{} found", search.results.len()));
+            }
+        });
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(false)
+            .sense(egui::Sense::click())
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .column(Column::auto())
+            .column(Column::remainder())
+            .max_scroll_height(f32::INFINITY)
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.strong("Name");
+                });
+                header.col(|ui| {
+                    ui.strong("Location");
+                });
+            })
+            .body(|body| {
+                body.rows(self.style.row_height, search.results.len(), |mut row| {
+                    let result = &search.results[row.index()];
+                    row.col(|ui| {
+                        ui.label(result.entry.get_icon());
+                        let name = result.entry.name.to_string_lossy();
+                        ui.label(highlighted_name(ui, &name, &result.positions, None));
+                    });
+                    row.col(|ui| {
+                        ui.weak(&result.location);
+                    });
+                    if row.response().clicked() {
+                        self.event_pool
+                            .emit_event(EventType::RevealEntry(result.entry.path.clone()));
+                    }
+                });
+            });
+
+        // keep polling while it runs
+        if search.running {
+            ui.ctx().request_repaint();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    // waits for the search to finish and returns the found paths
+    fn search(root: &Path, query: &str, options: SearchOptions) -> Vec<String> {
+        let query = Query::parse(query).unwrap();
+        let mut search = RecursiveSearch::start(root.to_path_buf(), query, options);
+        let start = Instant::now();
+        while search.running && start.elapsed() < Duration::from_secs(10) {
+            search.poll();
+            thread::sleep(Duration::from_millis(1));
+        }
+        let mut paths: Vec<String> = search
+            .results
+            .iter()
+            .map(|r| format!("{}/{}", r.location, r.entry.name.to_string_lossy()))
+            .collect();
+        paths.sort();
+        return paths;
+    }
+
+    #[test]
+    fn test_recursive_search() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/deep/deeper")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("Main.rs"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/deep/deeper/main.rs"), "").unwrap();
+        fs::write(root.join("target/main.rs"), "").unwrap();
+        fs::write(root.join(".hidden/main.rs"), "").unwrap();
+
+        let options = SearchOptions::default();
+        assert_eq!(
+            search(root, "MAIN", options.clone()),
+            vec!["./Main.rs", "src/deep/deeper/main.rs", "src/main.rs"]
+        );
+
+        let shallow = SearchOptions {
+            max_depth: 2,
+            ..options.clone()
+        };
+        assert_eq!(
+            search(root, "main", shallow),
+            vec!["./Main.rs", "src/main.rs"]
+        );
+
+        let everything = SearchOptions {
+            max_depth: 10,
+            skip_hidden: false,
+            respect_gitignore: false,
+        };
+        assert_eq!(search(root, "main.rs", everything).len(), 5);
+    }
+
+    #[test]
+    fn test_nested_repo_search() {
+        let dir = tempdir().unwrap();
+        let home = dir.path();
+        let proj = home.join("proj");
+        fs::create_dir_all(home.join(".git")).unwrap();
+        fs::create_dir_all(proj.join(".git")).unwrap();
+        fs::create_dir_all(proj.join("sub/.git")).unwrap();
+        fs::write(home.join(".gitignore"), "*\n").unwrap();
+        fs::write(proj.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(proj.join("a.log"), "").unwrap();
+        fs::write(proj.join("a.rs"), "").unwrap();
+        fs::write(proj.join("sub/b.log"), "").unwrap();
+
+        // git stops at the search root's repository, and the nested one
+        // doesn't inherit its rules
+        let options = SearchOptions {
+            skip_hidden: false,
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            search(&proj, "*", options.clone()),
+            vec!["./.gitignore", "./a.rs", "./sub", "sub/b.log"]
+        );
+
+        // outside of a repository, the parent's .gitignore doesn't apply
+        fs::remove_dir(home.join(".git")).unwrap();
+        fs::remove_dir(proj.join(".git")).unwrap();
+        let found = search(&proj, "a.*", options);
+        assert_eq!(found, vec!["./a.rs"]);
+    }
+}
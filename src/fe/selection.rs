@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::utils::dir::{EntryList, FeEntry};
+
+// how a click changes the selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectMode {
+    // select only the clicked entry
+    Replace,
+    // add or remove the clicked entry
+    Toggle,
+    // select the entries between the last clicked entry and this one
+    Range,
+}
+
+impl SelectMode {
+    pub fn from_modifiers(modifiers: egui::Modifiers) -> Self {
+        if modifiers.shift {
+            return SelectMode::Range;
+        }
+        if modifiers.command {
+            return SelectMode::Toggle;
+        }
+        return SelectMode::Replace;
+    }
+}
+
+// where a key press moves the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMove {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+// Selection is the set of selected entries of the current dir
+#[derive(Debug, Default)]
+pub struct Selection {
+    paths: HashSet<PathBuf>,
+    // the entry ranges start from
+    anchor: Option<PathBuf>,
+    // the last clicked entry, moved with the keyboard
+    cursor: Option<PathBuf>,
+}
+
+impl Selection {
+    pub fn contains(&self, path: &Path) -> bool {
+        return self.paths.contains(path);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.paths.is_empty();
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.anchor = None;
+        self.cursor = None;
+    }
+
+    // returns the entry under the cursor
+    pub fn cursor<'a>(&self, entries: &'a (impl EntryList + ?Sized)) -> Option<&'a FeEntry> {
+        let cursor = self.cursor.as_ref()?;
+        return entries.get(entries.position(cursor)?);
+    }
+
+    // moves the cursor and selects the entry under it like clicking it
+    // with `mode`. `page_len` is how many rows PageUp and PageDown skip.
+    // Returns the index of the entry under the cursor.
+    pub fn move_cursor(
+        &mut self,
+        movement: CursorMove,
+        mode: SelectMode,
+        entries: &(impl EntryList + ?Sized),
+        page_len: usize,
+    ) -> Option<usize> {
+        let last = entries.len().checked_sub(1)?;
+        let current = self
+            .cursor
+            .as_ref()
+            .and_then(|cursor| entries.position(cursor));
+
+        let index = match (current, movement) {
+            (_, CursorMove::Home) => 0,
+            (_, CursorMove::End) => last,
+            // without a cursor, moving down starts at the top and moving
+            // up at the bottom
+            (None, CursorMove::Down | CursorMove::PageDown) => 0,
+            (None, CursorMove::Up | CursorMove::PageUp) => last,
+            (Some(i), CursorMove::Up) => i.saturating_sub(1),
+            (Some(i), CursorMove::Down) => (i + 1).min(last),
+            (Some(i), CursorMove::PageUp) => i.saturating_sub(page_len),
+            (Some(i), CursorMove::PageDown) => (i + page_len).min(last),
+        };
+
+        let entry = entries.get(index)?.clone();
+        self.select(&entry, mode, entries);
+        return Some(index);
+    }
+
+    // updates the selection after clicking `entry`. `entries` are the
+    // entries in the order they're displayed, used to select ranges.
+    pub fn select(
+        &mut self,
+        entry: &FeEntry,
+        mode: SelectMode,
+        entries: &(impl EntryList + ?Sized),
+    ) {
+        self.cursor = Some(entry.path.clone());
+        match mode {
+            SelectMode::Replace => {
+                self.paths.clear();
+                self.paths.insert(entry.path.clone());
+                self.anchor = Some(entry.path.clone());
+            }
+            SelectMode::Toggle => {
+                if !self.paths.remove(&entry.path) {
+                    self.paths.insert(entry.path.clone());
+                }
+                self.anchor = Some(entry.path.clone());
+            }
+            SelectMode::Range => {
+                let index = |path: &Path| entries.position(path);
+                let anchor = self.anchor.as_deref().and_then(index);
+                let (anchor, target) = match (anchor, index(&entry.path)) {
+                    (Some(anchor), Some(target)) => (anchor, target),
+                    _ => return self.select(entry, SelectMode::Replace, entries),
+                };
+                // the anchor is kept, so the range can be resized
+                self.paths.clear();
+                let range = anchor.min(target)..=anchor.max(target);
+                let paths = range.filter_map(|index| entries.get(index));
+                self.paths.extend(paths.map(|e| e.path.clone()));
+            }
+        }
+    }
+
+    pub fn select_all(&mut self, entries: &(impl EntryList + ?Sized)) {
+        self.paths = entries.iter().map(|e| e.path.clone()).collect();
+    }
+
+    // drops the selected paths that aren't in `entries` anymore
+    pub fn retain(&mut self, entries: &(impl EntryList + ?Sized)) {
+        let paths: HashSet<&PathBuf> = entries.iter().map(|e| &e.path).collect();
+        self.paths.retain(|path| paths.contains(path));
+        if let Some(anchor) = &self.anchor {
+            if !paths.contains(anchor) {
+                self.anchor = None;
+            }
+        }
+        if let Some(cursor) = &self.cursor {
+            if !paths.contains(cursor) {
+                self.cursor = None;
+            }
+        }
+    }
+
+    // keeps the entry selected after it was renamed
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        if self.paths.remove(from) {
+            self.paths.insert(to.to_path_buf());
+        }
+        for path in [&mut self.anchor, &mut self.cursor].into_iter().flatten() {
+            if path == from {
+                *path = to.to_path_buf();
+            }
+        }
+    }
+
+    // returns the selected entries, in the order of `entries`
+    pub fn entries(&self, entries: &(impl EntryList + ?Sized)) -> Vec<FeEntry> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        return entries
+            .iter()
+            .filter(|e| self.contains(&e.path))
+            .cloned()
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::dir::{Dir, EntryKind};
+
+    fn entries(names: &[&str]) -> Vec<FeEntry> {
+        return names
+            .iter()
+            .map(|name| FeEntry {
+                name: name.into(),
+                path: PathBuf::from("/tmp").join(name),
+                entry_type: EntryKind::Dir(Dir { link: None }),
+            })
+            .collect();
+    }
+
+    fn names(selection: &Selection, entries: &[FeEntry]) -> Vec<String> {
+        return selection
+            .entries(entries)
+            .iter()
+            .map(|e| e.name.to_string_lossy().to_string())
+            .collect();
+    }
+
+    #[test]
+    fn test_selection() {
+        let entries = entries(&["a", "b", "c", "d", "e"]);
+        let mut selection = Selection::default();
+
+        selection.select(&entries[1], SelectMode::Replace, &entries);
+        selection.select(&entries[3], SelectMode::Toggle, &entries);
+        assert_eq!(names(&selection, &entries), vec!["b", "d"]);
+
+        // ranges start from the last clicked entry
+        selection.select(&entries[0], SelectMode::Range, &entries);
+        assert_eq!(names(&selection, &entries), vec!["a", "b", "c", "d"]);
+        selection.select(&entries[4], SelectMode::Range, &entries);
+        assert_eq!(names(&selection, &entries), vec!["d", "e"]);
+
+        selection.select(&entries[4], SelectMode::Toggle, &entries);
+        assert_eq!(names(&selection, &entries), vec!["d"]);
+
+        selection.select_all(&entries);
+        assert_eq!(selection.entries(&entries).len(), 5);
+
+        selection.retain(&entries[2..]);
+        assert_eq!(names(&selection, &entries), vec!["c", "d", "e"]);
+
+        selection.clear();
+        assert!(selection.is_empty());
+        // without an anchor, a range selects only the clicked entry
+        selection.select(&entries[2], SelectMode::Range, &entries);
+        assert_eq!(names(&selection, &entries), vec!["c"]);
+    }
+
+    #[test]
+    fn test_move_cursor() {
+        let entries = entries(&["a", "b", "c", "d", "e"]);
+        let mut selection = Selection::default();
+        let mut move_cursor = |movement, mode| {
+            return selection.move_cursor(movement, mode, &entries, 2);
+        };
+
+        assert_eq!(move_cursor(CursorMove::Up, SelectMode::Replace), Some(4));
+        assert_eq!(move_cursor(CursorMove::Down, SelectMode::Replace), Some(4));
+        assert_eq!(
+            move_cursor(CursorMove::PageUp, SelectMode::Replace),
+            Some(2)
+        );
+        assert_eq!(move_cursor(CursorMove::Up, SelectMode::Range), Some(1));
+        assert_eq!(move_cursor(CursorMove::PageUp, SelectMode::Range), Some(0));
+        assert_eq!(names(&selection, &entries), vec!["a", "b", "c"]);
+        assert_eq!(selection.cursor(&entries).unwrap().name, "a");
+
+        assert_eq!(
+            selection.move_cursor(CursorMove::End, SelectMode::Replace, &entries, 2),
+            Some(4)
+        );
+        assert_eq!(names(&selection, &entries), vec!["e"]);
+        assert_eq!(
+            selection.move_cursor(CursorMove::Home, SelectMode::Replace, &Vec::new(), 2),
+            None
+        );
+    }
+}
@@ -0,0 +1,665 @@
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::history::History;
+use super::loader::{DirLoader, LoadMessage};
+use super::query::{Query, QueryMatch};
+use super::search::{RecursiveSearch, SearchOptions};
+use super::selection::{CursorMove, SelectMode, Selection};
+use super::type_ahead::TypeAhead;
+use super::FE;
+use crate::events::EventType;
+use crate::utils;
+use crate::utils::dir::{path_to_fe_entry, DirSorting, EntryList, FeEntry, SortOrder};
+use crate::utils::trash::TrashItem;
+use crate::utils::watcher::DirChange;
+
+// Tab is a dir being browsed, with its own navigation state
+pub struct Tab {
+    pub path: PathBuf,
+    pub path_string: String,
+    // whether the path bar shows the path input instead of breadcrumbs
+    pub editing_path: bool,
+    // dirs completing the path being typed
+    pub path_completions: Vec<String>,
+    pub entries: Vec<FeEntry>,
+    // the indexes in `entries` of the entries left by the search, in the
+    // order they're shown. See `display_entries`.
+    pub display: Vec<usize>,
+
+    pub history: History,
+    pub dir_sorting: DirSorting,
+
+    // search state
+    pub search_txt: String,
+    // why the search text isn't a valid query
+    pub search_error: Option<String>,
+    // the chars of each displayed entry's name matching the search, empty
+    // without a search
+    pub display_highlights: Vec<Vec<usize>>,
+    // whether the search looks into subdirs too
+    pub recursive: bool,
+    pub search_options: SearchOptions,
+    pub recursive_search: Option<RecursiveSearch>,
+
+    pub selection: Selection,
+    // the row to scroll to in the next frame, set when the cursor moves
+    pub scroll_to_row: Option<usize>,
+    // prefix typed to jump to an entry
+    pub type_ahead: TypeAhead,
+
+    // when set, the trash is shown instead of the current dir
+    pub trash_items: Option<Vec<TrashItem>>,
+
+    // lists the dir in the background, while it loads
+    pub loader: Option<DirLoader>,
+    // the entry to select once the dir is loaded
+    pub pending_reveal: Option<PathBuf>,
+}
+
+impl Tab {
+    // creates a tab for `path`. Its entries aren't loaded yet.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path_string: path.to_string_lossy().to_string(),
+            path,
+            editing_path: false,
+            path_completions: Vec::new(),
+            entries: Vec::new(),
+            display: Vec::new(),
+            history: History::default(),
+            dir_sorting: DirSorting::FileNameAlphabetically(SortOrder::Asc),
+            search_txt: String::new(),
+            search_error: None,
+            display_highlights: Vec::new(),
+            recursive: false,
+            search_options: SearchOptions::default(),
+            recursive_search: None,
+            selection: Selection::default(),
+            scroll_to_row: None,
+            type_ahead: TypeAhead::default(),
+            trash_items: None,
+            loader: None,
+            pending_reveal: None,
+        }
+    }
+
+    // returns the name shown in the tab bar
+    pub fn title(&self) -> String {
+        if self.trash_items.is_some() {
+            return "Trash".to_string();
+        }
+        return match self.path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => self.path.to_string_lossy().to_string(),
+        };
+    }
+
+    // updates `path` and `path_string`, recording the previous dir in the
+    // history. Entries must be loaded afterwards.
+    pub fn set_path(&mut self, path: PathBuf) {
+        if path != self.path {
+            self.history.push(self.path.clone());
+        }
+        self.open(path);
+    }
+
+    // goes back `steps` dirs in the history. Returns whether the path
+    // changed, in which case entries must be loaded afterwards.
+    pub fn go_back(&mut self, steps: usize) -> bool {
+        return match self.history.go_back(self.path.clone(), steps) {
+            Some(path) => {
+                self.open(path);
+                true
+            }
+            None => false,
+        };
+    }
+
+    // goes forward `steps` dirs in the history, like `go_back`
+    pub fn go_forward(&mut self, steps: usize) -> bool {
+        return match self.history.go_forward(self.path.clone(), steps) {
+            Some(path) => {
+                self.open(path);
+                true
+            }
+            None => false,
+        };
+    }
+
+    // updates the path and resets the state of the previous dir
+    fn open(&mut self, path: PathBuf) {
+        self.path = path;
+        self.path_string = self.path.to_string_lossy().to_string();
+        self.search_txt = String::new();
+        self.recursive_search = None;
+        self.trash_items = None;
+        self.loader = None;
+        self.pending_reveal = None;
+        self.selection.clear();
+        self.type_ahead.clear();
+    }
+
+    // starts loading the files of current dir in the background. The old
+    // entries are shown until the first ones arrive, see `poll_loader`.
+    pub fn load_entries(&mut self) {
+        self.loader = Some(DirLoader::start(self.path.clone()));
+    }
+
+    // adds the entries loaded since the last call. Returns the errors met
+    // while loading, and whether it still runs.
+    pub fn poll_loader(&mut self) -> (Vec<String>, bool) {
+        let loader = match &mut self.loader {
+            Some(loader) => loader,
+            None => return (Vec::new(), false),
+        };
+        let (messages, running) = loader.poll();
+        let mut errors = Vec::new();
+        let mut entries = Vec::new();
+        for message in messages {
+            match message {
+                LoadMessage::Entries(batch) => entries.extend(batch),
+                LoadMessage::Error(err) => errors.push(err),
+            }
+        }
+
+        if !entries.is_empty() || !running {
+            if !loader.started {
+                loader.started = true;
+                self.entries.retain(|e| loader.changed.contains(&e.path));
+            }
+            loader.loaded += entries.len();
+            entries.retain(|e| !loader.changed.contains(&e.path));
+            self.entries.extend(entries);
+            self.update_sorting(self.dir_sorting.clone());
+        }
+        if !running {
+            self.loader = None;
+            self.selection.retain(&self.entries);
+            if let Some(path) = self.pending_reveal.take() {
+                self.reveal(&path);
+            }
+        }
+        return (errors, running);
+    }
+
+    // returns the entries left by the search, in the order they're shown
+    pub fn display_entries(&self) -> DisplayEntries<'_> {
+        return DisplayEntries {
+            entries: &self.entries,
+            indexes: &self.display,
+        };
+    }
+
+    // updates the selection after clicking `entry`, see `Selection::select`
+    pub fn select(&mut self, entry: &FeEntry, mode: SelectMode) {
+        let display = DisplayEntries {
+            entries: &self.entries,
+            indexes: &self.display,
+        };
+        self.selection.select(entry, mode, &display);
+    }
+
+    pub fn select_all(&mut self) {
+        let display = DisplayEntries {
+            entries: &self.entries,
+            indexes: &self.display,
+        };
+        self.selection.select_all(&display);
+    }
+
+    // moves the cursor, see `Selection::move_cursor`
+    pub fn move_cursor(
+        &mut self,
+        movement: CursorMove,
+        mode: SelectMode,
+        page_len: usize,
+    ) -> Option<usize> {
+        let display = DisplayEntries {
+            entries: &self.entries,
+            indexes: &self.display,
+        };
+        return self
+            .selection
+            .move_cursor(movement, mode, &display, page_len);
+    }
+
+    // returns the selected entries, in the order they're shown
+    pub fn selected_entries(&self) -> Vec<FeEntry> {
+        return self.selection.entries(&self.display_entries());
+    }
+
+    // selects the entry and scrolls to it
+    pub fn reveal(&mut self, path: &Path) {
+        let display = self.display_entries();
+        let found = display
+            .position(path)
+            .and_then(|index| Some((index, display.get(index)?.clone())));
+        if let Some((index, entry)) = found {
+            self.select(&entry, SelectMode::Replace);
+            self.scroll_to_row = Some(index);
+        }
+    }
+
+    // applies the changes to entries of the dir without reloading the
+    // others, then filters the entries once. The selection follows renamed
+    // entries.
+    pub fn apply_changes(&mut self, changes: &[DirChange]) {
+        for change in changes {
+            self.apply_change(change);
+        }
+        self.update_display_entries();
+        self.selection.retain(&self.entries);
+    }
+
+    fn apply_change(&mut self, change: &DirChange) {
+        match change {
+            DirChange::Created(path) | DirChange::Modified(path) => self.reload_entry(path),
+            DirChange::Deleted(path) => self.remove_entry(path),
+            DirChange::Renamed(from, to) => {
+                self.remove_entry(from);
+                self.selection.rename(from, to);
+                self.reload_entry(to);
+            }
+            DirChange::Reload(_) => self.load_entries(),
+        }
+    }
+
+    // removes the entry, and the copy the loader may still send
+    fn remove_entry(&mut self, path: &Path) {
+        let index = self.entries.iter().position(|e| e.path == path);
+        if let Some(index) = index {
+            self.entries.remove(index);
+        }
+        if let Some(loader) = &mut self.loader {
+            loader.changed.insert(path.to_path_buf());
+        }
+    }
+
+    // reads the entry again, keeping the entries sorted
+    fn reload_entry(&mut self, path: &Path) {
+        self.remove_entry(path);
+        // it may be gone already
+        let entry = match path_to_fe_entry(path) {
+            Ok(entry) => entry,
+            Err(_) => return,
+        };
+        let index = self.entries.partition_point(|e| {
+            utils::dir::compare_entries(e, &entry, &self.dir_sorting) == Ordering::Less
+        });
+        self.entries.insert(index, entry);
+    }
+
+    // update the sorting without reloading files from the file system
+    pub fn update_sorting(&mut self, sort: DirSorting) {
+        self.dir_sorting = sort;
+        self.entries
+            .sort_by(|a, b| utils::dir::compare_entries(a, b, &self.dir_sorting));
+        self.update_display_entries();
+    }
+
+    // filters the entries with the search, or starts searching the
+    // subdirs if the search is recursive.
+    pub fn update_search(&mut self) {
+        self.update_display_entries();
+        self.recursive_search = match Query::parse(&self.search_txt) {
+            Ok(query) if self.recursive && !query.is_empty() => Some(RecursiveSearch::start(
+                self.path.clone(),
+                query,
+                self.search_options.clone(),
+            )),
+            _ => None,
+        };
+    }
+
+    // filters the entries with the search query. Fuzzy matches are
+    // ranked best first, and invalid queries don't filter anything.
+    pub fn update_display_entries(&mut self) {
+        let query = match Query::parse(&self.search_txt) {
+            Ok(query) => {
+                self.search_error = None;
+                query
+            }
+            Err(err) => {
+                self.search_error = Some(err);
+                Query::default()
+            }
+        };
+        if query.is_empty() {
+            self.display = (0..self.entries.len()).collect();
+            self.display_highlights = Vec::new();
+            return;
+        }
+
+        let now = SystemTime::now();
+        let mut matches: Vec<(usize, QueryMatch)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| query.matches(e, now).map(|m| (i, m)))
+            .collect();
+        if query.is_ranked() {
+            // stable, so equal scores keep the dir sorting
+            matches.sort_by_key(|(_, m)| std::cmp::Reverse(m.score));
+        }
+        self.display = matches.iter().map(|(i, _)| *i).collect();
+        self.display_highlights = matches.into_iter().map(|(_, m)| m.positions).collect();
+    }
+}
+
+// DisplayEntries are the entries of a tab left by the search. They're
+// borrowed, so filtering huge dirs doesn't copy them.
+pub struct DisplayEntries<'a> {
+    entries: &'a [FeEntry],
+    indexes: &'a [usize],
+}
+
+impl EntryList for DisplayEntries<'_> {
+    fn len(&self) -> usize {
+        return self.indexes.len();
+    }
+
+    fn get(&self, index: usize) -> Option<&FeEntry> {
+        return self.entries.get(*self.indexes.get(index)?);
+    }
+}
+
+impl FE {
+    // opens a new tab on the current dir and switches to it
+    pub fn new_tab(&mut self) {
+        let mut tab = Tab::new(self.tab().path.clone());
+        tab.dir_sorting = self.tab().dir_sorting.clone();
+        let index = self.active_tab + 1;
+        self.tabs.insert(index, tab);
+        if let Some(panes) = &mut self.split {
+            for pane in panes.iter_mut().filter(|pane| **pane >= index) {
+                *pane += 1;
+            }
+        }
+        self.select_tab(index);
+        self.load_dir_entries();
+    }
+
+    // closes the tab, unless it's the last one
+    pub fn close_tab(&mut self, index: usize) {
+        if self.tabs.len() <= 1 || index >= self.tabs.len() {
+            return;
+        }
+        self.tabs.remove(index);
+        // closing a tab shown in a pane joins the panes
+        self.split = match self.split {
+            Some(panes) if !panes.contains(&index) => {
+                Some(panes.map(|pane| if pane > index { pane - 1 } else { pane }))
+            }
+            _ => None,
+        };
+        if self.active_tab > index || self.active_tab == self.tabs.len() {
+            self.select_tab(self.active_tab - 1);
+        } else {
+            self.select_tab(self.active_tab);
+        }
+    }
+
+    // shows the tab in the focused pane, or focuses the pane showing it
+    pub fn select_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        if let Some(panes) = &mut self.split {
+            if !panes.contains(&index) {
+                for pane in panes.iter_mut().filter(|pane| **pane == self.active_tab) {
+                    *pane = index;
+                }
+            }
+        }
+        self.active_tab = index;
+        self.renaming = None;
+    }
+
+    // draws the tab headers. Entries being dragged can be dropped on them.
+    pub fn draw_tabs(&mut self, ui: &mut egui::Ui) {
+        self.hovered_tab = None;
+        let hover_pos = ui.input(|i| i.pointer.hover_pos());
+
+        ui.horizontal(|ui| {
+            for (i, tab) in self.tabs.iter().enumerate() {
+                let header = ui
+                    .selectable_label(i == self.active_tab, tab.title())
+                    .on_hover_text(tab.path.to_string_lossy());
+                if header.clicked() {
+                    self.event_pool.emit_event(EventType::SelectTab(i));
+                }
+                if header.middle_clicked() {
+                    self.event_pool.emit_event(EventType::CloseTab(Some(i)));
+                }
+                if self.tabs.len() > 1 && ui.small_button("x").clicked() {
+                    self.event_pool.emit_event(EventType::CloseTab(Some(i)));
+                }
+
+                let dragging = !self.dragging_files.is_empty();
+                if dragging && hover_pos.is_some_and(|pos| header.rect.contains(pos)) {
+                    self.hovered_tab = Some(i);
+                    ui.painter()
+                        .rect_stroke(header.rect, 2.0, ui.visuals().selection.stroke);
+                }
+                ui.separator();
+            }
+            if ui.button("+").clicked() {
+                self.event_pool.emit_event(EventType::NewTab);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    // loads the entries of the tab and returns the errors met
+    fn load(tab: &mut Tab) -> Vec<String> {
+        tab.load_entries();
+        return wait_loaded(tab);
+    }
+
+    // polls the running loader until it's done
+    fn wait_loaded(tab: &mut Tab) -> Vec<String> {
+        let mut errors = Vec::new();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(10) {
+            let (new_errors, running) = tab.poll_loader();
+            errors.extend(new_errors);
+            if !running {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        return errors;
+    }
+
+    #[test]
+    fn test_tab_navigation() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("b.txt"), "b").unwrap();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+
+        let mut tab = Tab::new(dir.path().to_path_buf());
+        assert!(load(&mut tab).is_empty());
+        let names: Vec<_> = tab
+            .display_entries()
+            .iter()
+            .map(|e| e.name.clone())
+            .collect();
+        assert_eq!(names, vec!["sub", "a.txt", "b.txt"]);
+
+        tab.search_txt = "a.".to_string();
+        tab.update_display_entries();
+        assert_eq!(tab.display_entries().len(), 1);
+        assert_eq!(
+            tab.title(),
+            dir.path().file_name().unwrap().to_string_lossy()
+        );
+
+        tab.set_path(dir.path().join("sub"));
+        assert!(load(&mut tab).is_empty());
+        assert_eq!(
+            tab.history.back_list().next().map(|p| p.as_path()),
+            Some(dir.path())
+        );
+        assert_eq!(tab.search_txt, "");
+        assert_eq!(tab.display_entries().len(), 0);
+        assert_eq!(tab.title(), "sub");
+
+        assert!(tab.go_back(1));
+        assert_eq!(tab.path, dir.path());
+        assert!(tab.go_forward(1));
+        assert_eq!(tab.title(), "sub");
+        assert!(!tab.go_forward(1));
+
+        tab.set_path(dir.path().join("missing"));
+        assert!(!load(&mut tab).is_empty());
+    }
+
+    #[test]
+    fn test_load_entries() {
+        let dir = tempdir().unwrap();
+        for i in 0..2500 {
+            fs::write(dir.path().join(format!("{:04}", i)), "").unwrap();
+        }
+        let mut tab = Tab::new(dir.path().to_path_buf());
+        tab.pending_reveal = Some(dir.path().join("2000"));
+        assert!(load(&mut tab).is_empty());
+        assert_eq!(tab.display_entries().len(), 2500);
+        assert_eq!(tab.display_entries().get(0).unwrap().name, "0000");
+        assert!(tab.loader.is_none());
+        assert!(tab.selection.contains(&dir.path().join("2000")));
+        assert_eq!(tab.scroll_to_row, Some(2000));
+
+        // navigating away drops the stale load
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        tab.load_entries();
+        tab.set_path(dir.path().join("sub"));
+        assert!(tab.loader.is_none());
+        assert!(load(&mut tab).is_empty());
+        assert_eq!(tab.display_entries().len(), 0);
+    }
+
+    // lists a huge dir, reporting how long it takes. Too slow for the
+    // default run, use `cargo test --release -- --ignored --nocapture
+    // test_huge_dir` to benchmark.
+    #[test]
+    #[ignore]
+    fn test_huge_dir() {
+        const LEN: usize = 200_000;
+        let dir = tempdir().unwrap();
+        for i in 0..LEN {
+            fs::File::create(dir.path().join(format!("{:06}", i))).unwrap();
+        }
+        let mut tab = Tab::new(dir.path().to_path_buf());
+
+        let start = Instant::now();
+        assert!(load(&mut tab).is_empty());
+        println!("listed {} entries in {:?}", LEN, start.elapsed());
+        assert!(tab.loader.is_none());
+        assert_eq!(tab.display_entries().len(), LEN);
+        assert_eq!(tab.display_entries().get(LEN - 1).unwrap().name, "199999");
+
+        let start = Instant::now();
+        tab.search_txt = "19999*".to_string();
+        tab.update_display_entries();
+        println!("filtered {} entries in {:?}", LEN, start.elapsed());
+        assert_eq!(tab.display_entries().len(), 10);
+        assert_eq!(tab.display_highlights.len(), 10);
+    }
+
+    #[test]
+    fn test_change_while_loading() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("b.txt"), "b").unwrap();
+        let mut tab = Tab::new(root.to_path_buf());
+        tab.load_entries();
+
+        // the changes seen before the listing arrives are newer than it
+        fs::write(root.join("c.txt"), "c").unwrap();
+        tab.apply_changes(&[
+            DirChange::Created(root.join("c.txt")),
+            DirChange::Deleted(root.join("b.txt")),
+        ]);
+        assert!(wait_loaded(&mut tab).is_empty());
+        let names: Vec<_> = tab.entries.iter().map(|e| e.name.clone()).collect();
+        assert_eq!(names, vec!["a.txt", "c.txt"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_non_utf8_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join(OsStr::from_bytes(b"sub\xff"));
+        fs::create_dir(&sub).unwrap();
+        fs::write(sub.join(OsStr::from_bytes(b"caf\xe9")), "").unwrap();
+
+        let mut tab = Tab::new(dir.path().to_path_buf());
+        assert!(load(&mut tab).is_empty());
+        assert_eq!(tab.entries[0].path, sub);
+
+        // the path isn't read back from the lossy path bar
+        tab.set_path(sub.clone());
+        assert!(load(&mut tab).is_empty());
+        assert_eq!(tab.path, sub);
+        assert_eq!(tab.entries[0].name.as_bytes(), b"caf\xe9");
+        assert_eq!(tab.title(), "sub\u{FFFD}");
+    }
+
+    #[test]
+    fn test_apply_change() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("b.txt"), "b").unwrap();
+        fs::write(root.join("d.txt"), "d").unwrap();
+        let mut tab = Tab::new(root.to_path_buf());
+        assert!(load(&mut tab).is_empty());
+        let names = |tab: &Tab| -> Vec<String> {
+            return tab
+                .display_entries()
+                .iter()
+                .map(|e| e.name.to_string_lossy().to_string())
+                .collect();
+        };
+
+        let b = tab.entries[0].clone();
+        tab.select(&b, SelectMode::Replace);
+        fs::write(root.join("c.txt"), "c").unwrap();
+        tab.apply_changes(&[DirChange::Created(root.join("c.txt"))]);
+        assert_eq!(names(&tab), vec!["b.txt", "c.txt", "d.txt"]);
+
+        fs::rename(root.join("b.txt"), root.join("e.txt")).unwrap();
+        tab.apply_changes(&[DirChange::Renamed(root.join("b.txt"), root.join("e.txt"))]);
+        assert_eq!(names(&tab), vec!["c.txt", "d.txt", "e.txt"]);
+        assert!(tab.selection.contains(&root.join("e.txt")));
+
+        fs::write(root.join("c.txt"), "longer").unwrap();
+        tab.apply_changes(&[DirChange::Modified(root.join("c.txt"))]);
+        match &tab.entries[0].entry_type {
+            utils::dir::EntryKind::File(file) => assert_eq!(file.size, 6),
+            _ => panic!("c.txt is a file"),
+        }
+
+        fs::remove_file(root.join("d.txt")).unwrap();
+        // a change reported twice is applied once
+        tab.apply_changes(&[
+            DirChange::Deleted(root.join("d.txt")),
+            DirChange::Created(root.join("c.txt")),
+            DirChange::Created(root.join("c.txt")),
+        ]);
+        assert_eq!(names(&tab), vec!["c.txt", "e.txt"]);
+    }
+}
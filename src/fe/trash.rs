@@ -0,0 +1,106 @@
+use super::diagnostic::Diagnostic;
+use super::jobs::JobKind;
+use super::FE;
+use crate::events::EventType;
+use crate::utils::trash::{self, TrashItem};
+use egui_extras::{Column, TableBuilder};
+
+impl FE {
+    // lists the trash, which is then shown instead of the current dir
+    pub fn open_trash(&mut self) {
+        match trash::list_all() {
+            Ok(mut items) => {
+                // most recently deleted first
+                items.sort_by(|a, b| b.deletion_date.cmp(&a.deletion_date));
+                self.tab_mut().trash_items = Some(items);
+            }
+            Err(err) => self.diagnostics.push(Diagnostic::from_err(&err)),
+        }
+    }
+
+    pub fn restore_trash_item(&mut self, item: TrashItem) {
+        if let Err(err) = trash::restore(&item) {
+            self.diagnostics.push(Diagnostic::from_err(&err));
+        }
+        self.open_trash();
+    }
+
+    // asks for confirmation before permanently deleting the items
+    pub fn confirm_empty_trash(&mut self, items: Vec<TrashItem>) {
+        let message = match items.as_slice() {
+            [item] => format!("Permanently delete {}?", item.name.to_string_lossy()),
+            _ => format!("Permanently delete {} items from the trash?", items.len()),
+        };
+        self.confirm_job = Some((message, JobKind::EmptyTrash(items)));
+    }
+
+    pub fn draw_trash(&mut self, ui: &mut egui::Ui) {
+        let items = match &self.tabs[self.active_tab].trash_items {
+            Some(items) => items,
+            None => return,
+        };
+
+        ui.horizontal(|ui| {
+            ui.strong("🗑 Trash");
+            if ui
+                .add_enabled(!items.is_empty(), egui::Button::new("Empty trash"))
+                .clicked()
+            {
+                self.event_pool
+                    .emit_event(EventType::EmptyTrash(items.clone()));
+            }
+        });
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(false)
+            .sense(egui::Sense::click())
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::remainder())
+            .min_scrolled_height(0.0)
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.strong("Name");
+                });
+                header.col(|ui| {
+                    ui.strong("Original location");
+                });
+                header.col(|ui| {
+                    ui.strong("Deleted");
+                });
+            })
+            .body(|mut body| {
+                for item in items {
+                    body.row(self.style.row_height, |mut row| {
+                        let icon = if item.is_dir { "📁" } else { "📃" };
+                        row.col(|ui| {
+                            ui.label(format!("{} {}", icon, item.name.to_string_lossy()));
+                        });
+                        row.col(|ui| {
+                            let location =
+                                item.original_path.parent().unwrap_or(&item.original_path);
+                            ui.label(location.to_string_lossy());
+                        });
+                        row.col(|ui| {
+                            ui.label(&item.deletion_date);
+                        });
+
+                        row.response().context_menu(|ui| {
+                            if ui.button("Restore").clicked() {
+                                self.event_pool
+                                    .emit_event(EventType::RestoreTrashItem(item.clone()));
+                                ui.close_menu();
+                            }
+                            if ui.button("Delete permanently").clicked() {
+                                self.event_pool
+                                    .emit_event(EventType::EmptyTrash(vec![item.clone()]));
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                }
+            });
+    }
+}
@@ -0,0 +1,107 @@
+use std::time::{Duration, Instant};
+
+use crate::utils::dir::EntryList;
+
+// TypeAhead is the prefix typed while the file table has focus, used to
+// jump to the first entry starting with it.
+#[derive(Debug, Default)]
+pub struct TypeAhead {
+    prefix: String,
+    last_input: Option<Instant>,
+}
+
+impl TypeAhead {
+    // time without typing after which the prefix starts over
+    pub const TIMEOUT: Duration = Duration::from_millis(1000);
+
+    // appends `text` to the prefix, starting over if the user stopped
+    // typing for a while.
+    pub fn push(&mut self, text: &str, now: Instant) {
+        if self.prefix(now).is_none() {
+            self.prefix.clear();
+        }
+        self.prefix.push_str(text);
+        self.last_input = Some(now);
+    }
+
+    pub fn clear(&mut self) {
+        self.prefix.clear();
+        self.last_input = None;
+    }
+
+    // returns the prefix, unless it timed out
+    pub fn prefix(&self, now: Instant) -> Option<&str> {
+        let last_input = self.last_input?;
+        if self.prefix.is_empty() || now.duration_since(last_input) > Self::TIMEOUT {
+            return None;
+        }
+        return Some(&self.prefix);
+    }
+
+    // returns the index of the first entry that starts with the prefix
+    pub fn find(&self, entries: &(impl EntryList + ?Sized), now: Instant) -> Option<usize> {
+        let prefix = self.prefix(now)?;
+        return entries
+            .iter()
+            .position(|e| matched_len(&e.name.to_string_lossy(), prefix) > 0);
+    }
+}
+
+// returns how many chars of `name` match `prefix`, ignoring case, or 0 if
+// `name` doesn't start with `prefix`.
+pub fn matched_len(name: &str, prefix: &str) -> usize {
+    let mut name_chars = name.chars().flat_map(char::to_lowercase);
+    for c in prefix.chars().flat_map(char::to_lowercase) {
+        if name_chars.next() != Some(c) {
+            return 0;
+        }
+    }
+    return prefix.chars().count();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::dir::{Dir, EntryKind, FeEntry};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_type_ahead() {
+        let entries: Vec<FeEntry> = ["Cargo.toml", "src", "Crate", "README.md"]
+            .iter()
+            .map(|name| FeEntry {
+                name: name.into(),
+                path: PathBuf::from("/tmp").join(name),
+                entry_type: EntryKind::Dir(Dir { link: None }),
+            })
+            .collect();
+        let now = Instant::now();
+        let mut type_ahead = TypeAhead::default();
+        assert_eq!(type_ahead.find(&entries, now), None);
+
+        type_ahead.push("c", now);
+        assert_eq!(type_ahead.find(&entries, now), Some(0));
+        type_ahead.push("R", now);
+        assert_eq!(type_ahead.find(&entries, now), Some(2));
+        type_ahead.push("x", now);
+        assert_eq!(type_ahead.find(&entries, now), None);
+
+        // typing after the timeout starts a new prefix
+        let later = now + TypeAhead::TIMEOUT * 2;
+        assert_eq!(type_ahead.prefix(later), None);
+        type_ahead.push("r", later);
+        assert_eq!(type_ahead.prefix(later), Some("r"));
+        assert_eq!(type_ahead.find(&entries, later), Some(3));
+
+        type_ahead.clear();
+        assert_eq!(type_ahead.prefix(later), None);
+    }
+
+    #[test]
+    fn test_matched_len() {
+        assert_eq!(matched_len("README.md", "read"), 4);
+        assert_eq!(matched_len("README.md", "rm"), 0);
+        assert_eq!(matched_len("a", "ab"), 0);
+        assert_eq!(matched_len("Ábaco", "áb"), 2);
+    }
+}
@@ -9,6 +9,9 @@ mod utils;
 
 fn main() -> eframe::Result<()> {
     let args = cli::parse_args();
+    if let Some(terms) = &args.query {
+        std::process::exit(cli::print_query(&args, terms));
+    }
     let fe = fe::FE::from_args(args);
     fe.run()
 }
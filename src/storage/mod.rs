@@ -1,13 +1,29 @@
 use rusqlite::{Connection, Result as SqliteResult};
-use std::{ffi::OsString, fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use crate::fe::journal::{Journal, Operation};
 use crate::utils::dir::QuickAccessEntry;
+use crate::utils::file_index::{index_query, IndexChanges, IndexRoot, IndexedFile};
+use crate::utils::frecency::Visit;
+use crate::utils::path::{path_from_bytes, path_to_bytes};
 
 pub struct Storage {
     db: Connection,
 }
 
 const TABLE_NAME: &str = "quick_access";
+const JOURNAL_TABLE_NAME: &str = "journal";
+const VISITS_TABLE_NAME: &str = "visits";
+const INDEX_ROOTS_TABLE_NAME: &str = "index_roots";
+const FILE_INDEX_TABLE_NAME: &str = "file_index";
+
+const UNDO_STACK: &str = "undo";
+const REDO_STACK: &str = "redo";
 
 impl Storage {
     pub fn new(mut db_path: PathBuf) -> SqliteResult<Self> {
@@ -21,15 +37,98 @@ impl Storage {
             db_path,
             rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE | rusqlite::OpenFlags::SQLITE_OPEN_CREATE,
         )?;
+        // the indexer writes from its own connection
+        db.busy_timeout(Duration::from_secs(5))?;
 
         db.execute(
             &format!(
-                "CREATE TABLE IF NOT EXISTS {} (path TEXT PRIMARY KEY, name TEXT)",
+                "CREATE TABLE IF NOT EXISTS {} (path BLOB PRIMARY KEY, name BLOB)",
                 TABLE_NAME
             ),
             [],
         )?;
-        Ok(Self { db })
+        db.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    stack TEXT NOT NULL,
+                    operation BLOB NOT NULL
+                )",
+                JOURNAL_TABLE_NAME
+            ),
+            [],
+        )?;
+        db.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    path BLOB PRIMARY KEY,
+                    count INTEGER NOT NULL,
+                    last_visit INTEGER NOT NULL
+                )",
+                VISITS_TABLE_NAME
+            ),
+            [],
+        )?;
+        db.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    path BLOB PRIMARY KEY,
+                    last_indexed INTEGER NOT NULL
+                )",
+                INDEX_ROOTS_TABLE_NAME
+            ),
+            [],
+        )?;
+        // the trigram tokenizer matches any part of the names
+        db.execute(
+            &format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING fts5(
+                    name,
+                    path UNINDEXED,
+                    root UNINDEXED,
+                    is_dir UNINDEXED,
+                    size UNINDEXED,
+                    modified UNINDEXED,
+                    tokenize = 'trigram'
+                )",
+                FILE_INDEX_TABLE_NAME
+            ),
+            [],
+        )?;
+        let storage = Self { db };
+        storage.migrate()?;
+        Ok(storage)
+    }
+
+    // older versions stored paths as text, which can't hold the names
+    // that aren't valid UTF-8. They're converted to blobs once.
+    fn migrate(&self) -> SqliteResult<()> {
+        let version: i64 = self
+            .db
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version >= 1 {
+            return Ok(());
+        }
+
+        let tx = self.db.unchecked_transaction()?;
+        for (table, column) in [
+            (TABLE_NAME, "path"),
+            (TABLE_NAME, "name"),
+            (VISITS_TABLE_NAME, "path"),
+            (INDEX_ROOTS_TABLE_NAME, "path"),
+            (FILE_INDEX_TABLE_NAME, "path"),
+            (FILE_INDEX_TABLE_NAME, "root"),
+        ] {
+            tx.execute(
+                &format!(
+                    "UPDATE {0} SET {1} = CAST({1} AS BLOB) WHERE typeof({1}) = 'text'",
+                    table, column
+                ),
+                [],
+            )?;
+        }
+        tx.execute("PRAGMA user_version = 1", [])?;
+        tx.commit()
     }
 
     pub fn list_quick_access(&self) -> SqliteResult<Vec<QuickAccessEntry>> {
@@ -37,11 +136,9 @@ impl Storage {
             .db
             .prepare(&format!("SELECT path, name FROM {}", TABLE_NAME))?;
         let rows = stmt.query_map([], |row| {
-            let path: String = row.get(0)?;
-            let name: OsString = row.get::<usize, String>(1)?.into();
             Ok(QuickAccessEntry {
-                path: PathBuf::from(path),
-                name: name,
+                path: path_from_bytes(row.get(0)?),
+                name: path_from_bytes(row.get(1)?).into_os_string(),
             })
         })?;
 
@@ -59,21 +156,451 @@ impl Storage {
                 "INSERT OR REPLACE INTO {} (path, name) VALUES (?, ?)",
                 TABLE_NAME
             ),
-            &[
-                &entry.path.to_str().unwrap().to_string(),
-                &entry.name.to_string_lossy().to_string(),
+            [
+                path_to_bytes(&entry.path),
+                path_to_bytes(Path::new(&entry.name)),
             ],
         )?;
 
         Ok(())
     }
 
-    pub fn remove_quick_access(&self, path: &PathBuf) -> SqliteResult<()> {
+    pub fn remove_quick_access(&self, path: &Path) -> SqliteResult<()> {
         self.db.execute(
             &format!("DELETE FROM {} WHERE path = ?", TABLE_NAME),
-            &[path.to_str().unwrap()],
+            [path_to_bytes(path)],
+        )?;
+
+        Ok(())
+    }
+
+    // counts a visit to the dir at `time`, a unix timestamp
+    pub fn record_visit(&self, path: &Path, time: i64) -> SqliteResult<()> {
+        self.db.execute(
+            &format!(
+                "INSERT INTO {} (path, count, last_visit) VALUES (?, 1, ?)
+                ON CONFLICT(path) DO UPDATE SET count = count + 1, last_visit = excluded.last_visit",
+                VISITS_TABLE_NAME
+            ),
+            rusqlite::params![path_to_bytes(path), time],
         )?;
 
         Ok(())
     }
+
+    pub fn list_visits(&self) -> SqliteResult<Vec<Visit>> {
+        let mut stmt = self.db.prepare(&format!(
+            "SELECT path, count, last_visit FROM {}",
+            VISITS_TABLE_NAME
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Visit {
+                path: path_from_bytes(row.get(0)?),
+                count: row.get(1)?,
+                last_visit: row.get(2)?,
+            })
+        })?;
+
+        let mut visits = Vec::new();
+        for result in rows {
+            visits.push(result?);
+        }
+        Ok(visits)
+    }
+
+    pub fn remove_visit(&self, path: &Path) -> SqliteResult<()> {
+        self.db.execute(
+            &format!("DELETE FROM {} WHERE path = ?", VISITS_TABLE_NAME),
+            [path_to_bytes(path)],
+        )?;
+
+        Ok(())
+    }
+
+    // adds a dir to index. It's indexed on the next refresh.
+    pub fn add_index_root(&self, path: &Path) -> SqliteResult<()> {
+        self.db.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {} (path, last_indexed) VALUES (?, 0)",
+                INDEX_ROOTS_TABLE_NAME
+            ),
+            [path_to_bytes(path)],
+        )?;
+
+        Ok(())
+    }
+
+    // stops indexing the dir and forgets its files
+    pub fn remove_index_root(&self, path: &Path) -> SqliteResult<()> {
+        let path = path_to_bytes(path);
+        self.db.execute(
+            &format!("DELETE FROM {} WHERE path = ?", INDEX_ROOTS_TABLE_NAME),
+            [&path],
+        )?;
+        self.db.execute(
+            &format!("DELETE FROM {} WHERE root = ?", FILE_INDEX_TABLE_NAME),
+            [&path],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn list_index_roots(&self) -> SqliteResult<Vec<IndexRoot>> {
+        let mut stmt = self.db.prepare(&format!(
+            "SELECT path, last_indexed FROM {} ORDER BY path",
+            INDEX_ROOTS_TABLE_NAME
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok(IndexRoot {
+                path: path_from_bytes(row.get(0)?),
+                last_indexed: row.get(1)?,
+            })
+        })?;
+
+        let mut roots = Vec::new();
+        for result in rows {
+            roots.push(result?);
+        }
+        Ok(roots)
+    }
+
+    // returns the indexed files under the root by path, with their row id
+    pub fn list_indexed_files(
+        &self,
+        root: &Path,
+    ) -> SqliteResult<HashMap<PathBuf, (i64, IndexedFile)>> {
+        let mut stmt = self.db.prepare(&format!(
+            "SELECT rowid, name, path, is_dir, size, modified FROM {} WHERE root = ?",
+            FILE_INDEX_TABLE_NAME
+        ))?;
+        let rows = stmt.query_map([path_to_bytes(root)], |row| {
+            Ok((row.get::<usize, i64>(0)?, indexed_file_from_row(row, 1)?))
+        })?;
+
+        let mut files = HashMap::new();
+        for result in rows {
+            let (id, file) = result?;
+            files.insert(file.path.clone(), (id, file));
+        }
+        Ok(files)
+    }
+
+    // writes the changes of a refresh of the root, done at `time`
+    pub fn update_index(&self, root: &Path, changes: &IndexChanges, time: i64) -> SqliteResult<()> {
+        let root = path_to_bytes(root);
+        let tx = self.db.unchecked_transaction()?;
+        for id in &changes.removed {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE rowid = ?", FILE_INDEX_TABLE_NAME),
+                [id],
+            )?;
+        }
+        let updated = changes.updated.iter().map(|(id, file)| (Some(*id), file));
+        let added = changes.added.iter().map(|file| (None, file));
+        for (id, file) in updated.chain(added) {
+            tx.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO {} (rowid, name, path, root, is_dir, size, modified)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    FILE_INDEX_TABLE_NAME
+                ),
+                rusqlite::params![
+                    id,
+                    file.name,
+                    path_to_bytes(&file.path),
+                    root,
+                    file.is_dir,
+                    file.size as i64,
+                    file.modified
+                ],
+            )?;
+        }
+        tx.execute(
+            &format!(
+                "UPDATE {} SET last_indexed = ? WHERE path = ?",
+                INDEX_ROOTS_TABLE_NAME
+            ),
+            rusqlite::params![time, root],
+        )?;
+        tx.commit()
+    }
+
+    // finds the indexed files whose name contains every word of the
+    // text, shortest names first
+    pub fn search_index(&self, text: &str, limit: usize) -> SqliteResult<Vec<IndexedFile>> {
+        let (expression, patterns) = index_query(text);
+        let mut conditions = Vec::new();
+        let mut params: Vec<String> = Vec::new();
+        if let Some(expression) = expression {
+            conditions.push(format!("{} MATCH ?", FILE_INDEX_TABLE_NAME));
+            params.push(expression);
+        }
+        for pattern in patterns {
+            conditions.push("name LIKE ? ESCAPE '\\'".to_string());
+            params.push(pattern);
+        }
+        if conditions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = self.db.prepare(&format!(
+            "SELECT name, path, is_dir, size, modified FROM {} WHERE {}
+            ORDER BY length(name), name LIMIT {}",
+            FILE_INDEX_TABLE_NAME,
+            conditions.join(" AND "),
+            limit
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            indexed_file_from_row(row, 0)
+        })?;
+
+        let mut files = Vec::new();
+        for result in rows {
+            files.push(result?);
+        }
+        Ok(files)
+    }
+
+    pub fn load_journal(&self) -> SqliteResult<Journal> {
+        let undo = self.list_journal_stack(UNDO_STACK)?;
+        let redo = self.list_journal_stack(REDO_STACK)?;
+        Ok(Journal::new(undo, redo))
+    }
+
+    fn list_journal_stack(&self, stack: &str) -> SqliteResult<Vec<Operation>> {
+        let mut stmt = self.db.prepare(&format!(
+            "SELECT operation FROM {} WHERE stack = ? ORDER BY id",
+            JOURNAL_TABLE_NAME
+        ))?;
+        let rows = stmt.query_map([stack], |row| {
+            let blob: Vec<u8> = row.get(0)?;
+            bincode::deserialize(&blob).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Blob, err)
+            })
+        })?;
+
+        let mut operations = Vec::new();
+        for result in rows {
+            operations.push(result?);
+        }
+        Ok(operations)
+    }
+
+    // replaces the stored journal with `journal`
+    pub fn save_journal(&mut self, journal: &Journal) -> SqliteResult<()> {
+        let tx = self.db.transaction()?;
+        tx.execute(&format!("DELETE FROM {}", JOURNAL_TABLE_NAME), [])?;
+        for (stack, operations) in [
+            (UNDO_STACK, journal.undo_stack()),
+            (REDO_STACK, journal.redo_stack()),
+        ] {
+            for op in operations {
+                let blob = bincode::serialize(op)
+                    .map_err(|err| rusqlite::Error::ToSqlConversionFailure(err))?;
+                tx.execute(
+                    &format!(
+                        "INSERT INTO {} (stack, operation) VALUES (?, ?)",
+                        JOURNAL_TABLE_NAME
+                    ),
+                    rusqlite::params![stack, blob],
+                )?;
+            }
+        }
+        tx.commit()
+    }
+}
+
+// reads the name, path, is_dir, size and modified columns of an index
+// row, starting at `first`
+fn indexed_file_from_row(row: &rusqlite::Row, first: usize) -> SqliteResult<IndexedFile> {
+    Ok(IndexedFile {
+        name: row.get(first)?,
+        path: path_from_bytes(row.get(first + 1)?),
+        is_dir: row.get(first + 2)?,
+        size: row.get::<usize, i64>(first + 3)? as u64,
+        modified: row.get(first + 4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_journal_roundtrip() {
+        let dir = tempdir().unwrap();
+        let mut storage = Storage::new(dir.path().to_path_buf()).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(Operation::Create {
+            path: PathBuf::from("/tmp/a"),
+            is_dir: false,
+        });
+        journal.record(Operation::Trash(vec![PathBuf::from("/tmp/b")]));
+        let undone = journal.pop_undo().unwrap();
+        journal.push_redo(undone);
+        storage.save_journal(&journal).unwrap();
+
+        // reopening the db keeps the history
+        drop(storage);
+        let storage = Storage::new(dir.path().to_path_buf()).unwrap();
+        let loaded = storage.load_journal().unwrap();
+        assert_eq!(loaded.undo_stack(), journal.undo_stack());
+        assert_eq!(
+            loaded.redo_stack(),
+            &[Operation::Trash(vec![PathBuf::from("/tmp/b")])]
+        );
+    }
+
+    #[test]
+    fn test_visits() {
+        let dir = tempdir().unwrap();
+        let storage = Storage::new(dir.path().to_path_buf()).unwrap();
+        storage.record_visit(Path::new("/tmp"), 10).unwrap();
+        storage.record_visit(Path::new("/tmp/b"), 20).unwrap();
+        storage.record_visit(Path::new("/tmp"), 30).unwrap();
+        storage.remove_visit(Path::new("/tmp/b")).unwrap();
+
+        let visits = storage.list_visits().unwrap();
+        assert_eq!(
+            visits,
+            vec![Visit {
+                path: PathBuf::from("/tmp"),
+                count: 2,
+                last_visit: 30,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_file_index() {
+        let dir = tempdir().unwrap();
+        let storage = Storage::new(dir.path().to_path_buf()).unwrap();
+        let root = Path::new("/r");
+        storage.add_index_root(root).unwrap();
+        storage.add_index_root(Path::new("/other")).unwrap();
+
+        let file = |path: &str, size: u64| IndexedFile {
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            path: PathBuf::from(path),
+            is_dir: false,
+            size,
+            modified: 100,
+        };
+        let found = vec![
+            file("/r/main.rs", 1),
+            file("/r/src/domain_model.rs", 2),
+            file("/r/README.md", 3),
+        ];
+        let changes = IndexChanges::diff(storage.list_indexed_files(root).unwrap(), found);
+        storage.update_index(root, &changes, 50).unwrap();
+
+        let names = |text: &str| -> Vec<String> {
+            return storage
+                .search_index(text, 10)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.name)
+                .collect();
+        };
+        assert_eq!(names("MAIN"), vec!["main.rs", "domain_model.rs"]);
+        assert_eq!(names("main mod"), vec!["domain_model.rs"]);
+        assert_eq!(names("rs"), vec!["main.rs", "domain_model.rs"]);
+        assert!(names("").is_empty());
+        assert_eq!(
+            storage.list_index_roots().unwrap(),
+            vec![
+                IndexRoot {
+                    path: PathBuf::from("/other"),
+                    last_indexed: 0
+                },
+                IndexRoot {
+                    path: root.to_path_buf(),
+                    last_indexed: 50
+                },
+            ]
+        );
+
+        // a refresh only writes what changed
+        let found = vec![file("/r/main.rs", 10), file("/r/README.md", 3)];
+        let changes = IndexChanges::diff(storage.list_indexed_files(root).unwrap(), found);
+        assert_eq!((changes.added.len(), changes.updated.len()), (0, 1));
+        assert_eq!(changes.removed.len(), 1);
+        storage.update_index(root, &changes, 60).unwrap();
+        assert!(names("model").is_empty());
+        assert_eq!(storage.search_index("main", 10).unwrap()[0].size, 10);
+
+        storage.remove_index_root(root).unwrap();
+        assert!(names("readme").is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_non_utf8_paths() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let mut storage = Storage::new(dir.path().to_path_buf()).unwrap();
+        let name = OsStr::from_bytes(b"caf\xe9");
+        let path = Path::new("/tmp").join(name);
+
+        let entry = QuickAccessEntry {
+            path: path.clone(),
+            name: name.to_os_string(),
+        };
+        storage.save_quick_access(&entry).unwrap();
+        let entries = storage.list_quick_access().unwrap();
+        assert_eq!((&entries[0].path, &entries[0].name), (&path, &entry.name));
+        storage.remove_quick_access(&path).unwrap();
+        assert!(storage.list_quick_access().unwrap().is_empty());
+
+        storage.record_visit(&path, 10).unwrap();
+        assert_eq!(storage.list_visits().unwrap()[0].path, path);
+
+        let mut journal = Journal::default();
+        journal.record(Operation::Move(vec![(path.clone(), path.join("b"))]));
+        storage.save_journal(&journal).unwrap();
+        assert_eq!(
+            storage.load_journal().unwrap().undo_stack(),
+            journal.undo_stack()
+        );
+
+        let file = IndexedFile {
+            name: name.to_string_lossy().to_string(),
+            path: path.join(name),
+            is_dir: false,
+            size: 1,
+            modified: 0,
+        };
+        let changes = IndexChanges::diff(HashMap::new(), vec![file.clone()]);
+        storage.update_index(&path, &changes, 10).unwrap();
+        assert_eq!(storage.search_index("caf", 10).unwrap(), vec![file]);
+        assert_eq!(storage.list_indexed_files(&path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_migrate_text_paths() {
+        let dir = tempdir().unwrap();
+        let db = Connection::open(dir.path().join("db")).unwrap();
+        db.execute(
+            "CREATE TABLE quick_access (path TEXT PRIMARY KEY, name TEXT)",
+            [],
+        )
+        .unwrap();
+        db.execute("INSERT INTO quick_access VALUES ('/tmp/a', 'a')", [])
+            .unwrap();
+        drop(db);
+
+        let storage = Storage::new(dir.path().to_path_buf()).unwrap();
+        let entries = storage.list_quick_access().unwrap();
+        assert_eq!(entries[0].path, PathBuf::from("/tmp/a"));
+        storage.remove_quick_access(Path::new("/tmp/a")).unwrap();
+        assert!(storage.list_quick_access().unwrap().is_empty());
+    }
 }
@@ -1,9 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::ffi::OsString;
-#[cfg(target_os = "unix")]
+#[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use std::{fs, io};
 
@@ -18,8 +18,30 @@ pub struct QuickAccessEntry {
     pub path: PathBuf,
 }
 
+// Link is where a symlink points to
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Dir {}
+pub struct Link {
+    // as stored in the link, it may be relative to the link's dir
+    pub target: PathBuf,
+    // whether the target is missing
+    pub broken: bool,
+}
+
+impl Link {
+    pub fn description(&self) -> String {
+        let target = self.target.to_string_lossy();
+        if self.broken {
+            return format!("Broken link to {}", target);
+        }
+        return format!("Link to {}", target);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dir {
+    // set if the entry is a link to the dir
+    pub link: Option<Link>,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct File {
@@ -28,6 +50,8 @@ pub struct File {
     // file size in bytes
     pub size: u64,
     pub modified: SystemTime,
+    // set if the entry is a link to the file. Broken links are files.
+    pub link: Option<Link>,
 }
 
 impl File {
@@ -61,13 +85,13 @@ impl File {
         };
 
         for cmd in cmds {
-            if cmd.clickable == None {
+            if cmd.clickable.is_none() {
                 continue;
             }
 
             if let Some(exts) = &cmd.extensions {
                 if exts.contains(&ext) {
-                    return Some(EventType::RunFileCmd(cmd.clone(), self.path.clone()));
+                    return Some(EventType::RunFileCmd(cmd.clone(), vec![self.path.clone()]));
                 }
             }
         }
@@ -76,12 +100,22 @@ impl File {
     }
 }
 
+// EntryKind is what the entry is, or what it points to for links
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EntryKind {
     Dir(Dir),
     File(File),
 }
 
+impl EntryKind {
+    pub fn link(&self) -> Option<&Link> {
+        return match self {
+            EntryKind::Dir(dir) => dir.link.as_ref(),
+            EntryKind::File(file) => file.link.as_ref(),
+        };
+    }
+}
+
 // FeEntry represents an entry in the file explorer.
 // It is mostly a subset of fs::DirEntry, but we abstract
 // away the properties we don't need to make it easier to handle
@@ -95,6 +129,9 @@ pub struct FeEntry {
 
 impl FeEntry {
     pub fn get_icon(&self) -> String {
+        if let Some(link) = self.entry_type.link() {
+            return if link.broken { "⚠" } else { "🔗" }.to_string();
+        }
         return match self.entry_type {
             EntryKind::Dir(_) => "📁".to_string(),
             EntryKind::File(_) => "📃".to_string(),
@@ -102,26 +139,116 @@ impl FeEntry {
     }
 }
 
+// EntryList is a list of entries in the order they're displayed. It lets
+// filtered views borrow the entries instead of copying them.
+pub trait EntryList {
+    fn len(&self) -> usize;
+    fn get(&self, index: usize) -> Option<&FeEntry>;
+
+    fn iter(&self) -> impl Iterator<Item = &FeEntry> {
+        return (0..self.len()).filter_map(|index| self.get(index));
+    }
+
+    // returns the index of the entry with `path`
+    fn position(&self, path: &Path) -> Option<usize> {
+        return self.iter().position(|e| e.path == path);
+    }
+}
+
+impl EntryList for [FeEntry] {
+    fn len(&self) -> usize {
+        return <[FeEntry]>::len(self);
+    }
+
+    fn get(&self, index: usize) -> Option<&FeEntry> {
+        return <[FeEntry]>::get(self, index);
+    }
+}
+
+impl EntryList for Vec<FeEntry> {
+    fn len(&self) -> usize {
+        return Vec::len(self);
+    }
+
+    fn get(&self, index: usize) -> Option<&FeEntry> {
+        return self.as_slice().get(index);
+    }
+}
+
 pub fn fs_to_fe_entry(fs_entry: fs::DirEntry) -> Result<FeEntry, io::Error> {
     let file_type = fs_entry.file_type()?;
     let metadata = fs_entry.metadata()?;
+    return entry_from_metadata(fs_entry.path(), fs_entry.file_name(), file_type, &metadata);
+}
+
+// reads the entry at `path`, like `fs_to_fe_entry` does for dir entries
+pub fn path_to_fe_entry(path: &Path) -> Result<FeEntry, io::Error> {
+    let metadata = fs::symlink_metadata(path)?;
+    let name = match path.file_name() {
+        Some(name) => name.to_os_string(),
+        None => return Err(io::Error::other("path has no file name")),
+    };
+    return entry_from_metadata(path.to_path_buf(), name, metadata.file_type(), &metadata);
+}
+
+// builds the entry from the metadata of the entry itself. Links are
+// followed, so they're shown as what they point to.
+fn entry_from_metadata(
+    path: PathBuf,
+    name: OsString,
+    file_type: fs::FileType,
+    metadata: &fs::Metadata,
+) -> Result<FeEntry, io::Error> {
+    if !file_type.is_symlink() {
+        return build_entry(path, name, file_type, metadata, None);
+    }
+    let target = fs::read_link(&path)?;
+    return match fs::metadata(&path) {
+        Ok(followed) => {
+            let link = Link {
+                target,
+                broken: false,
+            };
+            build_entry(path, name, followed.file_type(), &followed, Some(link))
+        }
+        // the target is missing, or the links form a loop
+        Err(_) => {
+            let link = Link {
+                target,
+                broken: true,
+            };
+            build_entry(path, name, file_type, metadata, Some(link))
+        }
+    };
+}
+
+fn build_entry(
+    path: PathBuf,
+    name: OsString,
+    file_type: fs::FileType,
+    metadata: &fs::Metadata,
+    link: Option<Link>,
+) -> Result<FeEntry, io::Error> {
     let is_dir = file_type.is_dir();
     let modified = metadata.modified()?;
 
     let entry_type = if is_dir {
-        EntryKind::Dir(Dir {})
+        EntryKind::Dir(Dir { link })
     } else {
+        let broken = link.as_ref().is_some_and(|link| link.broken);
         EntryKind::File(File {
-            path: fs_entry.path(),
-            is_exe: is_exe(&fs_entry),
+            path: path.clone(),
+            // the permissions of a link aren't the target's
+            is_exe: !broken && is_exe(&path, file_type, metadata),
             size: metadata.len(),
-            modified: modified,
+            modified,
+            link,
         })
     };
 
     return Ok(FeEntry {
-        name: fs_entry.file_name(),
-        path: fs_entry.path(),
+        name,
+        path,
         entry_type,
     });
 }
@@ -225,7 +352,7 @@ pub fn compare_entries(a: &FeEntry, b: &FeEntry, sorting: &DirSorting) -> Orderi
 
 // returns a valid file name. If a file with that name already exists
 // among the entries, append `-2`. If a `-2` already exists, append `-3` and so on.
-pub fn get_valid_new_file(new_file_name: &OsString, entries: &Vec<FeEntry>) -> OsString {
+pub fn get_valid_new_file(new_file_name: &OsString, entries: &[FeEntry]) -> OsString {
     let mut valid_name = new_file_name.clone();
     let mut counter = 1;
 
@@ -240,10 +367,79 @@ pub fn get_valid_new_file(new_file_name: &OsString, entries: &Vec<FeEntry>) -> O
     return valid_name;
 }
 
+// checks that `entry` can be renamed to `name`: it must be a single path
+// component, and no other entry can have that name.
+pub fn validate_new_name(
+    name: &OsString,
+    entry: &FeEntry,
+    entries: &[FeEntry],
+) -> Result<(), String> {
+    let name_str = name.to_string_lossy();
+    if name_str.trim().is_empty() {
+        return Err("name can't be empty".to_string());
+    }
+    if name == "." || name == ".." {
+        return Err(format!("{} isn't a valid name", name_str));
+    }
+    if name_str.contains('/') || name_str.contains(std::path::MAIN_SEPARATOR) {
+        return Err("name can't contain path separators".to_string());
+    }
+    if name_str.contains('\0') {
+        return Err("name can't contain null characters".to_string());
+    }
+    if entries
+        .iter()
+        .any(|e| &e.name == name && e.path != entry.path)
+    {
+        return Err(format!("{} already exists", name_str));
+    }
+    return Ok(());
+}
+
 fn append_suffix(base: &OsString, counter: usize) -> OsString {
-    let mut new_name = base.clone().into_string().unwrap_or_default();
-    new_name.push_str(&format!("-{}", counter));
-    OsString::from(new_name)
+    let mut new_name = base.clone();
+    new_name.push(format!("-{}", counter));
+    new_name
+}
+
+// unix only needs the metadata, windows only the name and type
+#[cfg_attr(unix, allow(unused_variables))]
+#[cfg_attr(windows, allow(unused_variables))]
+pub fn is_exe(path: &Path, file_type: fs::FileType, metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        let permissions = metadata.permissions();
+        // On Unix, check the execute bits
+        return permissions.mode() & 0o111 != 0;
+    }
+
+    #[cfg(windows)]
+    {
+        if file_type.is_file() {
+            // Simple heuristic: check if the file has an executable extension
+            if let Some(extension) = path.extension() {
+                let extensions = ["exe", "bat", "cmd", "com"];
+                return extensions.iter().any(|&ext| extension == ext);
+            } else {
+                return false;
+            }
+        } else {
+            return false;
+        }
+    }
+}
+
+pub fn get_parent(path: PathBuf) -> Option<FeEntry> {
+    match path.parent() {
+        None => return None,
+        Some(parent) => {
+            return Some(FeEntry {
+                name: "..".into(),
+                path: parent.to_path_buf(),
+                entry_type: EntryKind::Dir(Dir { link: None }),
+            })
+        }
+    }
 }
 
 #[cfg(test)]
@@ -362,46 +558,91 @@ mod tests {
         // TODO test other sortings
     }
 
-    // TODO:
-    // test_fs_to_fe_entry
-}
+    #[test]
+    fn test_validate_new_name() {
+        let dir = tempdir().unwrap();
+        let dir_path = dir.path();
+        create_file(&dir_path.join("a.txt"));
+        create_file(&dir_path.join("b.txt"));
+
+        let entries: Vec<FeEntry> = fs::read_dir(dir_path)
+            .unwrap()
+            .map(|e| fs_to_fe_entry(e.unwrap()).unwrap())
+            .collect();
+        let a = entries.iter().find(|e| e.name == "a.txt").unwrap();
+
+        assert!(validate_new_name(&"c.txt".into(), a, &entries).is_ok());
+        // keeping the same name isn't a collision
+        assert!(validate_new_name(&"a.txt".into(), a, &entries).is_ok());
+        assert!(validate_new_name(&"b.txt".into(), a, &entries).is_err());
+        assert!(validate_new_name(&"".into(), a, &entries).is_err());
+        assert!(validate_new_name(&"..".into(), a, &entries).is_err());
+        assert!(validate_new_name(&"dir/c.txt".into(), a, &entries).is_err());
+    }
 
-pub fn is_exe(fs_entry: &fs::DirEntry) -> bool {
+    #[test]
     #[cfg(unix)]
-    {
-        let metadata = fs_entry.metadata().unwrap();
-        let permissions = metadata.permissions();
-        // On Unix, check the execute bits
-        return permissions.mode() & 0o111 != 0;
+    fn test_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        create_file(&dir.path().join(name));
+
+        let entry = path_to_fe_entry(&dir.path().join(name)).unwrap();
+        assert_eq!(entry.name, name);
+        // the suffix of a copy keeps the bytes of the name
+        let copy_name = get_valid_new_file(&entry.name, std::slice::from_ref(&entry));
+        assert_eq!(copy_name.as_bytes(), b"caf\xe9.txt-2");
     }
 
-    #[cfg(windows)]
-    {
-        let file_type = fs_entry.file_type().unwrap();
-        if file_type.is_file() {
-            // Simple heuristic: check if the file has an executable extension
-            let path = fs_entry.path();
-            if let Some(extension) = path.extension() {
-                let extensions = ["exe", "bat", "cmd", "com"];
-                return extensions.iter().any(|&ext| extension == ext);
-            } else {
-                return false;
+    #[test]
+    #[cfg(unix)]
+    fn test_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        create_dir(&root.join("dir"));
+        fs::write(root.join("file"), "abc").unwrap();
+        symlink("dir", root.join("dir link")).unwrap();
+        symlink(root.join("file"), root.join("file link")).unwrap();
+        symlink("missing", root.join("broken link")).unwrap();
+
+        let entry = |name: &str| path_to_fe_entry(&root.join(name)).unwrap();
+        // links are shown as what they point to
+        match entry("dir link").entry_type {
+            EntryKind::Dir(dir) => assert_eq!(
+                dir.link,
+                Some(Link {
+                    target: PathBuf::from("dir"),
+                    broken: false
+                })
+            ),
+            _ => panic!("a link to a dir is a dir"),
+        }
+        match entry("file link").entry_type {
+            EntryKind::File(file) => {
+                assert_eq!(file.size, 3);
+                assert_eq!(file.link.unwrap().target, root.join("file"));
             }
-        } else {
-            return false;
+            _ => panic!("a link to a file is a file"),
         }
-    }
-}
+        let broken = entry("broken link");
+        assert!(broken.entry_type.link().unwrap().broken);
+        assert_eq!(broken.get_icon(), "⚠");
+        assert!(entry("dir").entry_type.link().is_none());
 
-pub fn get_parent(path: PathBuf) -> Option<FeEntry> {
-    match path.parent() {
-        None => return None,
-        Some(parent) => {
-            return Some(FeEntry {
-                name: "..".into(),
-                path: parent.to_path_buf(),
-                entry_type: EntryKind::Dir(Dir {}),
-            })
-        }
+        // dir entries are followed too
+        let entries: Vec<FeEntry> = fs::read_dir(root)
+            .unwrap()
+            .map(|e| fs_to_fe_entry(e.unwrap()).unwrap())
+            .collect();
+        let dir_link = entries.iter().find(|e| e.name == "dir link").unwrap();
+        assert!(matches!(dir_link.entry_type, EntryKind::Dir(_)));
+        assert_eq!(dir_link.get_icon(), "🔗");
     }
+
+    // TODO:
+    // test_fs_to_fe_entry
 }
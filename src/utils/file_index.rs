@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+// IndexRoot is a dir whose files are indexed
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexRoot {
+    pub path: PathBuf,
+    // unix timestamp of the last refresh, 0 if it never ran
+    pub last_indexed: i64,
+}
+
+// IndexedFile is an entry of the file index
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    // unix timestamp
+    pub modified: i64,
+}
+
+impl IndexedFile {
+    // reads the metadata of the dir entry
+    pub fn from_dir_entry(entry: &fs::DirEntry) -> Option<Self> {
+        let metadata = entry.metadata().ok()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs() as i64);
+        return Some(Self {
+            path: entry.path(),
+            name: entry.file_name().to_string_lossy().to_string(),
+            is_dir: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified,
+        });
+    }
+}
+
+// IndexChanges are the rows a refresh writes to the index
+#[derive(Debug, Default)]
+pub struct IndexChanges {
+    pub added: Vec<IndexedFile>,
+    // the row ids with the new state of the files
+    pub updated: Vec<(i64, IndexedFile)>,
+    pub removed: Vec<i64>,
+}
+
+impl IndexChanges {
+    // compares the files found on disk with the indexed ones, keyed by
+    // path with their row id. Files that didn't change aren't written.
+    pub fn diff(
+        mut indexed: HashMap<PathBuf, (i64, IndexedFile)>,
+        found: Vec<IndexedFile>,
+    ) -> Self {
+        let mut changes = Self::default();
+        for file in found {
+            match indexed.remove(&file.path) {
+                Some((_, old)) if old == file => {}
+                Some((id, _)) => changes.updated.push((id, file)),
+                None => changes.added.push(file),
+            }
+        }
+        changes.removed = indexed.into_values().map(|(id, _)| id).collect();
+        changes.removed.sort();
+        return changes;
+    }
+}
+
+// turns the words of the search box into an FTS5 expression matching
+// names containing all of them. Words shorter than a trigram can't use
+// the index, they're returned as LIKE patterns instead.
+pub fn index_query(text: &str) -> (Option<String>, Vec<String>) {
+    let mut phrases = Vec::new();
+    let mut patterns = Vec::new();
+    for word in text.split_whitespace() {
+        if word.chars().count() >= 3 {
+            phrases.push(format!("\"{}\"", word.replace('"', "\"\"")));
+        } else {
+            let escaped = word
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            patterns.push(format!("%{}%", escaped));
+        }
+    }
+    let expression = match phrases.is_empty() {
+        true => None,
+        false => Some(phrases.join(" AND ")),
+    };
+    return (expression, patterns);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64) -> IndexedFile {
+        let path = PathBuf::from(path);
+        return IndexedFile {
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            path,
+            is_dir: false,
+            size,
+            modified: 0,
+        };
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut indexed = HashMap::new();
+        indexed.insert(PathBuf::from("/r/same"), (1, file("/r/same", 1)));
+        indexed.insert(PathBuf::from("/r/changed"), (2, file("/r/changed", 1)));
+        indexed.insert(PathBuf::from("/r/gone"), (3, file("/r/gone", 1)));
+        let found = vec![file("/r/same", 1), file("/r/changed", 2), file("/r/new", 1)];
+
+        let changes = IndexChanges::diff(indexed, found);
+        assert_eq!(changes.added, vec![file("/r/new", 1)]);
+        assert_eq!(changes.updated, vec![(2, file("/r/changed", 2))]);
+        assert_eq!(changes.removed, vec![3]);
+        let changes = IndexChanges::diff(HashMap::new(), Vec::new());
+        assert!(changes.added.is_empty() && changes.removed.is_empty());
+    }
+
+    #[test]
+    fn test_index_query() {
+        assert_eq!(
+            index_query("main rs"),
+            (Some("\"main\"".to_string()), vec!["%rs%".to_string()])
+        );
+        assert_eq!(
+            index_query("a\"b\"c 5%"),
+            (
+                Some("\"a\"\"b\"\"c\"".to_string()),
+                vec!["%5\\%%".to_string()]
+            )
+        );
+        assert_eq!(index_query("  "), (None, Vec::new()));
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+
+// Visit is how often and when a dir was opened, to jump back to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub path: PathBuf,
+    pub count: u32,
+    // unix timestamp, in seconds
+    pub last_visit: i64,
+}
+
+impl Visit {
+    // ranks dirs visited often and recently first, like zoxide
+    pub fn frecency(&self, now: i64) -> f64 {
+        let age = now - self.last_visit;
+        let weight = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        return self.count as f64 * weight;
+    }
+}
+
+// checks whether the terms match the path, in order and ignoring case.
+// Each term only needs its chars to appear in order, but the last one
+// must match the dir name so `proj` finds `~/projects` and not its subdirs.
+pub fn matches(terms: &[String], path: &str) -> bool {
+    let path = path.to_lowercase();
+    let name_start = path
+        .trim_end_matches(['/', '\\'])
+        .rfind(['/', '\\'])
+        .map_or(0, |i| i + 1);
+
+    let mut rest = path.as_str();
+    let mut offset = 0;
+    for (i, term) in terms.iter().enumerate() {
+        let term = term.to_lowercase();
+        // the last term is only looked up in the dir name
+        if i == terms.len() - 1 && offset < name_start {
+            rest = &path[name_start..];
+            offset = name_start;
+        }
+        match match_subsequence(&term, rest) {
+            Some(end) => {
+                rest = &rest[end..];
+                offset += end;
+            }
+            None => return false,
+        }
+    }
+    return true;
+}
+
+// returns where the chars of `term` end in `text`, if they all appear
+// in order.
+fn match_subsequence(term: &str, text: &str) -> Option<usize> {
+    let mut end = 0;
+    let mut chars = text.char_indices();
+    for c in term.chars() {
+        let (i, found) = chars.find(|(_, t)| *t == c)?;
+        end = i + found.len_utf8();
+    }
+    return Some(end);
+}
+
+// returns the visits matching the terms, best first. No terms match
+// every visit.
+pub fn rank(visits: Vec<Visit>, terms: &[String], now: i64) -> Vec<Visit> {
+    let mut ranked: Vec<Visit> = visits
+        .into_iter()
+        .filter(|visit| terms.is_empty() || matches(terms, &visit.path.to_string_lossy()))
+        .collect();
+    ranked.sort_by(|a, b| b.frecency(now).total_cmp(&a.frecency(now)));
+    return ranked;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(query: &str) -> Vec<String> {
+        return query.split_whitespace().map(String::from).collect();
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(&terms("proj"), "/home/user/projects"));
+        assert!(matches(&terms("PRJ"), "/home/user/projects/"));
+        assert!(matches(&terms("user proj"), "/home/user/projects"));
+        assert!(!matches(&terms("proj user"), "/home/user/projects"));
+        // the last term matches the dir name
+        assert!(!matches(&terms("proj"), "/home/user/projects/fe"));
+        assert!(matches(&terms("proj fe"), "/home/user/projects/fe"));
+        assert!(!matches(&terms("xyz"), "/home/user/projects"));
+    }
+
+    #[test]
+    fn test_rank() {
+        let now = 10 * WEEK;
+        let visit = |path: &str, count, age| Visit {
+            path: PathBuf::from(path),
+            count,
+            last_visit: now - age,
+        };
+        let visits = vec![
+            visit("/old/projects", 8, 2 * WEEK),
+            visit("/recent/projects", 2, 10),
+            visit("/often/projects", 5, 2 * DAY),
+            visit("/other", 50, 10),
+        ];
+
+        let ranked = rank(visits.clone(), &terms("proj"), now);
+        let paths: Vec<_> = ranked.iter().map(|v| v.path.to_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            vec!["/recent/projects", "/often/projects", "/old/projects"]
+        );
+        assert_eq!(rank(visits, &[], now)[0].path, PathBuf::from("/other"));
+    }
+}
@@ -0,0 +1,136 @@
+// scores of the fuzzy matcher. Matches right after each other or at the
+// start of a word score higher, chars skipped in between lower the score.
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 12;
+const BONUS_WORD_START: i64 = 10;
+const PENALTY_GAP: i64 = 1;
+
+// FuzzyMatch is how well a pattern matches a text, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    // the indexes of the matched chars in the text
+    pub positions: Vec<usize>,
+}
+
+// matches the chars of `pattern` in order in `text`, ignoring case.
+// Returns the best scoring match, or None if some char is missing.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+    let original: Vec<char> = text.chars().collect();
+    // lowercasing may change the number of chars, in which case
+    // positions are approximate
+    let text: Vec<char> = original
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    if pattern.len() > text.len() {
+        return None;
+    }
+
+    let n = text.len();
+    // best[i][j] is the best score with pattern[i] matched at text[j],
+    // and from[i][j] where pattern[i - 1] was matched for it
+    let mut best = vec![vec![None; n]; pattern.len()];
+    let mut from = vec![vec![0; n]; pattern.len()];
+    for (i, p) in pattern.iter().enumerate() {
+        // best score of the previous char matched before j - 1, with the
+        // gap up to j - 1 already paid
+        let mut running: Option<(i64, usize)> = None;
+        for j in 0..n {
+            if i > 0 && j >= 1 {
+                if let Some((score, k)) = running {
+                    running = Some((score - PENALTY_GAP, k));
+                }
+            }
+            if i > 0 && j >= 2 {
+                if let Some(score) = best[i - 1][j - 2] {
+                    // matching at j skips j - 1
+                    let score = score - PENALTY_GAP;
+                    if running.is_none_or(|(r, _)| score > r) {
+                        running = Some((score, j - 2));
+                    }
+                }
+            }
+            if text[j] != *p {
+                continue;
+            }
+
+            let bonus = SCORE_MATCH + word_start_bonus(&original, j);
+            if i == 0 {
+                best[i][j] = Some(bonus - PENALTY_GAP * j as i64);
+                continue;
+            }
+            let consecutive = j
+                .checked_sub(1)
+                .and_then(|k| best[i - 1][k])
+                .map(|score| (score + BONUS_CONSECUTIVE, j - 1));
+            let candidate = match (consecutive, running) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, k)) = candidate {
+                best[i][j] = Some(score + bonus);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut j, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|s| (j, s)))
+        .max_by_key(|(j, s)| (*s, std::cmp::Reverse(*j)))?;
+    let mut positions = vec![j];
+    for i in (1..=last).rev() {
+        j = from[i][j];
+        positions.push(j);
+    }
+    positions.reverse();
+    return Some(FuzzyMatch { score, positions });
+}
+
+// scores chars starting a word higher: the first one, the ones after a
+// separator, and uppercase ones after a lowercase one.
+fn word_start_bonus(text: &[char], index: usize) -> i64 {
+    let prev = match index.checked_sub(1) {
+        Some(prev) => text[prev],
+        None => return BONUS_WORD_START,
+    };
+    let c = text[index];
+    if !prev.is_alphanumeric() || (prev.is_lowercase() && c.is_uppercase()) {
+        return BONUS_WORD_START;
+    }
+    return 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let m = fuzzy_match("cfgtoml", "config.toml").unwrap();
+        assert_eq!(m.positions, vec![0, 3, 5, 7, 8, 9, 10]);
+        assert!(fuzzy_match("tomlcfg", "config.toml").is_none());
+        assert!(fuzzy_match("CFG", "config").is_some());
+        assert_eq!(fuzzy_match("", "x").unwrap().positions, Vec::<usize>::new());
+
+        // consecutive matches and word starts win
+        let m = fuzzy_match("main", "my_animal_main.rs").unwrap();
+        assert_eq!(m.positions, vec![10, 11, 12, 13]);
+        let m = fuzzy_match("fb", "fooBar").unwrap();
+        assert_eq!(m.positions, vec![0, 3]);
+
+        let exact = fuzzy_match("read", "README.md").unwrap();
+        let scattered = fuzzy_match("read", "rebuild_and.txt").unwrap();
+        assert!(exact.score > scattered.score);
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::glob::glob_match;
+
+// Rule is a pattern of a .gitignore file
+#[derive(Debug)]
+struct Rule {
+    pattern: String,
+    // `!pattern` re-includes what a previous rule ignored
+    negated: bool,
+    // `pattern/` only matches dirs
+    dir_only: bool,
+    // patterns with a '/' match the path from the .gitignore dir,
+    // the others match the name at any depth
+    anchored: bool,
+}
+
+// Gitignore holds the rules of a .gitignore file
+#[derive(Debug)]
+pub struct Gitignore {
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl Gitignore {
+    // parses the content of the .gitignore file in `base`
+    pub fn parse(base: &Path, content: &str) -> Self {
+        let mut rules = Vec::new();
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            rules.push(Rule {
+                pattern: line.trim_start_matches('/').to_string(),
+                negated,
+                dir_only,
+                anchored,
+            });
+        }
+        return Self {
+            base: base.to_path_buf(),
+            rules,
+        };
+    }
+
+    // reads the .gitignore file of the dir, if any
+    pub fn from_dir(dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        return Some(Self::parse(dir, &content));
+    }
+
+    // returns whether the path is ignored by these rules, or None if no
+    // rule matches it.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let name = path.file_name()?.to_string_lossy();
+        // the last matching rule wins
+        for rule in self.rules.iter().rev() {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let matched = if rule.anchored {
+                glob_match(&rule.pattern, &relative)
+            } else {
+                glob_match(&rule.pattern, &name)
+            };
+            if matched {
+                return Some(!rule.negated);
+            }
+        }
+        return None;
+    }
+}
+
+// returns the .gitignore files of the dirs above `dir` that apply to it,
+// from the root of its git repository down. There are none when `dir` is
+// the root of a repository or isn't in one.
+pub fn parents(dir: &Path) -> Vec<Gitignore> {
+    if is_repo_root(dir) {
+        return Vec::new();
+    }
+    let mut gitignores = Vec::new();
+    for ancestor in dir.ancestors().skip(1) {
+        gitignores.extend(Gitignore::from_dir(ancestor));
+        if is_repo_root(ancestor) {
+            gitignores.reverse();
+            return gitignores;
+        }
+    }
+    return Vec::new();
+}
+
+// returns whether the dir is the root of a git repository
+pub fn is_repo_root(dir: &Path) -> bool {
+    return dir.join(".git").exists();
+}
+
+// checks whether the path is ignored by any of the .gitignore files,
+// the deeper ones taking precedence.
+pub fn is_ignored(gitignores: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+    for gitignore in gitignores.iter().rev() {
+        if let Some(ignored) = gitignore.matched(path, is_dir) {
+            return ignored;
+        }
+    }
+    return false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_gitignore() {
+        let root = Path::new("/repo");
+        let gitignore = Gitignore::parse(
+            root,
+            "# build output\ntarget/\n*.log\n!keep.log\n/docs/*.html\n",
+        );
+        let sub = Gitignore::parse(&root.join("sub"), "!debug.log\n");
+        let gitignores = [gitignore, sub];
+
+        assert!(is_ignored(&gitignores, &root.join("target"), true));
+        assert!(!is_ignored(&gitignores, &root.join("target"), false));
+        assert!(is_ignored(&gitignores, &root.join("a/b/err.log"), false));
+        assert!(!is_ignored(&gitignores, &root.join("keep.log"), false));
+        assert!(is_ignored(
+            &gitignores,
+            &root.join("docs/index.html"),
+            false
+        ));
+        assert!(!is_ignored(
+            &gitignores,
+            &root.join("a/docs/index.html"),
+            false
+        ));
+        assert!(!is_ignored(&gitignores, &root.join("sub/debug.log"), false));
+        assert!(!is_ignored(&gitignores, &root.join("src/main.rs"), false));
+    }
+
+    #[test]
+    fn test_parents() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "*").unwrap();
+        fs::write(repo.join(".gitignore"), "*.log").unwrap();
+        fs::write(repo.join("src/.gitignore"), "*.tmp").unwrap();
+
+        // the .gitignore outside of the repository doesn't apply, and the
+        // one of the dir itself isn't a parent
+        let gitignores = parents(&repo.join("src"));
+        assert_eq!(gitignores.len(), 1);
+        assert!(is_ignored(&gitignores, &repo.join("src/a.log"), false));
+        assert!(!is_ignored(&gitignores, &repo.join("src/a.tmp"), false));
+        assert!(!is_ignored(&gitignores, &repo.join("src/a.rs"), false));
+        assert!(parents(&repo).is_empty());
+
+        fs::remove_dir(repo.join(".git")).unwrap();
+        assert!(parents(&repo.join("src")).is_empty());
+    }
+}
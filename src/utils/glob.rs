@@ -0,0 +1,101 @@
+// checks whether `text` matches the glob `pattern`. `*` matches anything
+// but a '/', `**` matches anything, `?` matches a single char and `[...]`
+// matches a char of the set, like `[a-z]` or `[!0-9]`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    return match_from(&pattern, &text);
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    let (first, rest) = match pattern.split_first() {
+        Some(split) => split,
+        None => return text.is_empty(),
+    };
+    match first {
+        '*' => {
+            let across_dirs = rest.first() == Some(&'*');
+            let rest = if across_dirs { &rest[1..] } else { rest };
+            // `**/` also matches no dir at all
+            if across_dirs && rest.first() == Some(&'/') && match_from(&rest[1..], text) {
+                return true;
+            }
+            for i in 0..=text.len() {
+                if match_from(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' && !across_dirs {
+                    return false;
+                }
+            }
+            return false;
+        }
+        '?' => {
+            return !text.is_empty() && text[0] != '/' && match_from(rest, &text[1..]);
+        }
+        '[' => {
+            let c = match text.first() {
+                Some(c) => *c,
+                None => return false,
+            };
+            return match match_class(rest, c) {
+                Some((true, len)) => match_from(&rest[len..], &text[1..]),
+                Some((false, _)) => false,
+                // no closing ']', match it literally
+                None => c == '[' && match_from(rest, &text[1..]),
+            };
+        }
+        '\\' if !rest.is_empty() => {
+            return text.first() == Some(&rest[0]) && match_from(&rest[1..], &text[1..]);
+        }
+        c => {
+            return text.first() == Some(c) && match_from(rest, &text[1..]);
+        }
+    }
+}
+
+// matches `c` against the set after a '['. Returns whether it matched and
+// the length of the set including the ']'.
+fn match_class(class: &[char], c: char) -> Option<(bool, usize)> {
+    let negated = matches!(class.first(), Some('!') | Some('^'));
+    let start = negated as usize;
+    // a ']' right after the '[' is part of the set
+    let end = start + 1 + class.get(start + 1..)?.iter().position(|c| *c == ']')?;
+    let set = &class[start..end];
+
+    let mut matched = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            matched |= set[i] <= c && c <= set[i + 2];
+            i += 3;
+        } else {
+            matched |= set[i] == c;
+            i += 1;
+        }
+    }
+    return Some((matched != negated, end + 1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "main.rs.bak"));
+        assert!(!glob_match("*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/*.rs", "src/fe/draw/file.rs"));
+        assert!(glob_match("**/target", "a/b/target"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(glob_match("[a-c]*", "banana"));
+        assert!(!glob_match("[!a-c]*", "banana"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("a[", "a["));
+        assert!(glob_match("\\*", "*"));
+        assert!(!glob_match("\\*", "a"));
+    }
+}
@@ -1,13 +1,21 @@
 use chrono::{self, DateTime, Local};
 use std::{
     io,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Child, Command},
     time::SystemTime,
 };
 
 pub mod dir;
+pub mod file_index;
+pub mod frecency;
+pub mod fuzzy;
+pub mod gitignore;
+pub mod glob;
+pub mod path;
 pub mod term;
+pub mod trash;
+pub mod watcher;
 
 pub fn human_readable_size(bytes: u64) -> String {
     let kilobyte = 1024u64;
@@ -24,6 +32,71 @@ pub fn human_readable_size(bytes: u64) -> String {
     }
 }
 
+pub fn run_exe(path: &PathBuf) -> io::Result<Child> {
+    return Command::new(path).spawn();
+}
+
+// opens the file with the default application of the system
+pub fn open_default(path: &Path) -> io::Result<Child> {
+    #[cfg(target_os = "windows")]
+    return Command::new("cmd.exe")
+        .args(["/C", "start", ""])
+        .arg(path)
+        .spawn();
+    #[cfg(target_os = "macos")]
+    return Command::new("open").arg(path).spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    return Command::new("xdg-open").arg(path).spawn();
+}
+
+pub fn system_time_to_human_readable(time: SystemTime) -> String {
+    let datetime: DateTime<Local> = time.into();
+    return datetime.format("%Y-%m-%d %H:%M:%S").to_string();
+}
+
+// percent-encodes every byte of the path that isn't allowed
+// unescaped in a URI path.
+pub fn percent_encode_path(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    };
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().replace('\\', "/").into_bytes();
+
+    let mut encoded = String::new();
+    for b in bytes {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    return encoded;
+}
+
+// decodes a path encoded with `percent_encode_path`
+pub fn percent_decode_path(encoded: &str) -> PathBuf {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    return path::path_from_bytes(decoded);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,13 +131,12 @@ mod tests {
         assert_eq!(human_readable_size(1023), "1023 b");
         assert_eq!(human_readable_size(1024), "1 kB");
     }
-}
-
-pub fn run_exe(path: &PathBuf) -> io::Result<Child> {
-    return Command::new(path).spawn();
-}
 
-pub fn system_time_to_human_readable(time: SystemTime) -> String {
-    let datetime: DateTime<Local> = time.into();
-    return datetime.format("%Y-%m-%d %H:%M:%S").to_string();
+    #[test]
+    fn test_percent_decode_path() {
+        let path = Path::new("/tmp/a b/ç#1%");
+        assert_eq!(percent_decode_path(&percent_encode_path(path)), path);
+        // invalid escapes are kept as is
+        assert_eq!(percent_decode_path("a%zz%2"), PathBuf::from("a%zz%2"));
+    }
 }
@@ -0,0 +1,249 @@
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+
+// expands `~`, `~user`, `$VAR` and `${VAR}` in a path typed by the user,
+// and resolves it against `cwd` when it's relative.
+pub fn expand_path(input: &str, cwd: &Path) -> Result<PathBuf, String> {
+    let expanded = expand_tilde(&expand_vars(input.trim(), |name| env::var(name).ok())?)?;
+    let path = PathBuf::from(expanded);
+    if path.is_absolute() {
+        return Ok(normalize(&path));
+    }
+    return Ok(normalize(&cwd.join(path)));
+}
+
+// replaces a leading `~` or `~user` with the user's home dir
+fn expand_tilde(input: &str) -> Result<String, String> {
+    let rest = match input.strip_prefix('~') {
+        Some(rest) => rest,
+        None => return Ok(input.to_string()),
+    };
+    let end = rest.find(is_separator).unwrap_or(rest.len());
+    let (user, rest) = rest.split_at(end);
+    let home = if user.is_empty() {
+        home::home_dir().ok_or("can't find the home dir")?
+    } else {
+        user_home(user).ok_or(format!("unknown user {}", user))?
+    };
+    return Ok(format!("{}{}", home.to_string_lossy(), rest));
+}
+
+// returns the home dir of another user
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<PathBuf> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = CString::new(user).ok()?;
+    // the result points to static memory, copy it right away
+    let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+    if passwd.is_null() {
+        return None;
+    }
+    let dir = unsafe { CStr::from_ptr((*passwd).pw_dir) };
+    return Some(PathBuf::from(std::ffi::OsStr::from_bytes(dir.to_bytes())));
+}
+
+#[cfg(not(unix))]
+fn user_home(_user: &str) -> Option<PathBuf> {
+    return None;
+}
+
+// replaces `$VAR` and `${VAR}` with the value `lookup` gives the var
+fn expand_vars(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        let mut name = String::new();
+        if chars.next_if_eq(&'{').is_some() {
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(format!("missing '}}' after ${{{}", name)),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                name.push(c);
+            }
+        }
+        // a lone '$' is kept as is
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        match lookup(&name) {
+            Some(value) => expanded.push_str(&value),
+            None => return Err(format!("undefined variable {}", name)),
+        }
+    }
+    return Ok(expanded);
+}
+
+// resolves `.` and `..` without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    return normalized;
+}
+
+fn is_separator(c: char) -> bool {
+    return c == '/' || c == MAIN_SEPARATOR;
+}
+
+// lists the dirs matching the last component of the typed path, as the
+// completed input. Hidden dirs are only listed if the component starts
+// with a '.'.
+pub fn complete_path(input: &str, cwd: &Path) -> Vec<String> {
+    let (head, prefix) = match input.rfind(is_separator) {
+        Some(index) => input.split_at(index + 1),
+        None => ("", input),
+    };
+    let dir = match expand_path(head, cwd) {
+        Ok(dir) => dir,
+        Err(_) => return Vec::new(),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let prefix = prefix.to_lowercase();
+    let mut completions: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.to_lowercase().starts_with(&prefix))
+        .filter(|name| !name.starts_with('.') || prefix.starts_with('.'))
+        .map(|name| format!("{}{}{}", head, name, MAIN_SEPARATOR))
+        .collect();
+    completions.sort_by_key(|completion| completion.to_lowercase());
+    return completions;
+}
+
+// returns the longest prefix shared by all the completions
+pub fn common_prefix(completions: &[String]) -> String {
+    let first = match completions.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let mut len = first.len();
+    for completion in &completions[1..] {
+        len = first
+            .char_indices()
+            .zip(completion.chars())
+            .take_while(|((i, a), b)| *i < len && a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0);
+    }
+    return first[..len].to_string();
+}
+
+// returns the bytes of the path, to store it without losing the names
+// that aren't valid UTF-8. They're only lossless on Unix.
+pub fn path_to_bytes(path: &Path) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        return path.as_os_str().as_bytes().to_vec();
+    }
+    #[cfg(not(unix))]
+    {
+        return path.to_string_lossy().into_owned().into_bytes();
+    }
+}
+
+// reads a path stored with `path_to_bytes`
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        return PathBuf::from(std::ffi::OsString::from_vec(bytes));
+    }
+    #[cfg(not(unix))]
+    {
+        return PathBuf::from(String::from_utf8_lossy(&bytes).into_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_expand_path() {
+        let cwd = Path::new("/tmp/cwd");
+        let home = home::home_dir().unwrap();
+        assert_eq!(expand_path("~", cwd).unwrap(), home);
+        assert_eq!(expand_path("~/docs", cwd).unwrap(), home.join("docs"));
+        assert_eq!(
+            expand_path("~root", cwd).unwrap(),
+            user_home("root").unwrap()
+        );
+        assert!(expand_path("~no-such-user-fe", cwd).is_err());
+
+        assert_eq!(expand_path("a/./b", cwd).unwrap(), cwd.join("a/b"));
+        assert_eq!(expand_path("../a", cwd).unwrap(), PathBuf::from("/tmp/a"));
+        assert_eq!(expand_path("/a/b/..", cwd).unwrap(), PathBuf::from("/a"));
+    }
+
+    #[test]
+    fn test_expand_vars() {
+        let vars = HashMap::from([("DIR".to_string(), "/var/lib".to_string())]);
+        let lookup = |name: &str| vars.get(name).cloned();
+        assert_eq!(expand_vars("$DIR/x", lookup).unwrap(), "/var/lib/x");
+        assert_eq!(expand_vars("${DIR}x", lookup).unwrap(), "/var/libx");
+        assert_eq!(expand_vars("a$/b", lookup).unwrap(), "a$/b");
+        assert!(expand_vars("$UNDEFINED", lookup).is_err());
+        assert!(expand_vars("${DIR", lookup).is_err());
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("Documents")).unwrap();
+        fs::create_dir(dir.path().join("downloads")).unwrap();
+        fs::create_dir(dir.path().join(".dots")).unwrap();
+        fs::write(dir.path().join("data.txt"), "").unwrap();
+
+        let sep = MAIN_SEPARATOR;
+        let completions = complete_path("d", dir.path());
+        assert_eq!(
+            completions,
+            vec![format!("Documents{}", sep), format!("downloads{}", sep)]
+        );
+        assert_eq!(common_prefix(&completions), "");
+        assert_eq!(
+            complete_path(".", dir.path()),
+            vec![format!(".dots{}", sep)]
+        );
+
+        let input = format!("{}{}do", dir.path().to_string_lossy(), sep);
+        let completions = complete_path(&input, dir.path());
+        assert_eq!(completions.len(), 2);
+        assert_eq!(
+            common_prefix(&completions),
+            format!("{}{}", dir.path().to_string_lossy(), sep)
+        );
+        assert_eq!(complete_path("missing/", dir.path()), Vec::<String>::new());
+        assert_eq!(common_prefix(&["abc".to_string(), "abd".to_string()]), "ab");
+    }
+}
@@ -1,9 +1,16 @@
+use std::path::Path;
 use std::process::Command;
 
 #[cfg(target_os = "windows")]
-pub fn open_terminal(path: &str) -> Option<std::io::Error> {
+pub fn open_terminal(path: &Path) -> Option<std::io::Error> {
     if let Err(e) = Command::new("cmd.exe")
-        .args(&["/C", "start", "cmd.exe", "/K", &format!("cd /D {}", path)])
+        .args(&[
+            "/C",
+            "start",
+            "cmd.exe",
+            "/K",
+            &format!("cd /D {}", path.display()),
+        ])
         .spawn()
     {
         return Some(e);
@@ -12,7 +19,7 @@ pub fn open_terminal(path: &str) -> Option<std::io::Error> {
 }
 
 #[cfg(target_os = "macos")]
-pub fn open_terminal(path: &str) -> Option<std::io::Error> {
+pub fn open_terminal(path: &Path) -> Option<std::io::Error> {
     if let Err(e) = Command::new("open")
         .arg("-a")
         .arg("Terminal")
@@ -25,9 +32,9 @@ pub fn open_terminal(path: &str) -> Option<std::io::Error> {
 }
 
 #[cfg(target_os = "linux")]
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 #[cfg(target_os = "linux")]
-pub fn open_terminal(path: &str) -> Option<std::io::Error> {
+pub fn open_terminal(path: &Path) -> Option<std::io::Error> {
     // TODO: this whole thing needs to be improved.
     // Allacrity, for instance, should have --hold to persist
     // if we close the app.
@@ -50,5 +57,5 @@ pub fn open_terminal(path: &str) -> Option<std::io::Error> {
         }
     }
 
-    Some(Error::new(ErrorKind::Other, "failed to open terminal"))
+    Some(Error::other("failed to open terminal"))
 }
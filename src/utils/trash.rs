@@ -0,0 +1,451 @@
+// Trash implements the freedesktop.org Trash specification:
+// https://specifications.freedesktop.org/trash-spec/trashspec-latest.html
+use chrono::Local;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::utils;
+
+const INFO_EXT: &str = ".trashinfo";
+
+// TrashItem is an entry sitting in one of the trash dirs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashItem {
+    // the trash dir holding the item, with its `files` and `info` subdirs
+    pub trash_dir: PathBuf,
+    // name of the item inside `files`
+    pub name: OsString,
+    pub original_path: PathBuf,
+    pub deletion_date: String,
+    pub is_dir: bool,
+}
+
+impl TrashItem {
+    // path of the trashed entry
+    pub fn path(&self) -> PathBuf {
+        return self.trash_dir.join("files").join(&self.name);
+    }
+
+    fn info_path(&self) -> PathBuf {
+        let mut info_name = self.name.clone();
+        info_name.push(INFO_EXT);
+        return self.trash_dir.join("info").join(info_name);
+    }
+}
+
+// returns the trash dir of the user's home, $XDG_DATA_HOME/Trash
+pub fn home_trash() -> Option<PathBuf> {
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
+        let data_home = PathBuf::from(data_home);
+        if data_home.is_absolute() {
+            return Some(data_home.join("Trash"));
+        }
+    }
+    return home::home_dir().map(|home| home.join(".local/share/Trash"));
+}
+
+// moves `path` to the trash dir of its volume
+pub fn trash(path: &Path) -> io::Result<()> {
+    let home_trash =
+        home_trash().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home trash dir"))?;
+    return trash_in(path, &home_trash);
+}
+
+fn trash_in(path: &Path, home_trash: &Path) -> io::Result<()> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("can't trash {}", path.display()),
+        )
+    };
+    let name = path.file_name().ok_or_else(invalid)?;
+    let parent = path.parent().ok_or_else(invalid)?;
+    // don't canonicalize the path itself, that would follow symlinks
+    let path = fs::canonicalize(parent)?.join(name);
+
+    let (trash_dir, topdir) = trash_dir_for(&path, home_trash)?;
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    fs::create_dir_all(&files_dir)?;
+    fs::create_dir_all(&info_dir)?;
+
+    // items in a volume's trash dir are stored relative to its top dir
+    let original_path = match &topdir {
+        Some(topdir) => path.strip_prefix(topdir).unwrap_or(&path),
+        None => &path,
+    };
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        utils::percent_encode_path(original_path),
+        Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+
+    // the info file is created first, since creating it atomically is what
+    // reserves the name across processes.
+    let mut counter = 1;
+    loop {
+        let mut trashed_name = name.to_os_string();
+        if counter > 1 {
+            trashed_name.push(format!(".{}", counter));
+        }
+        counter += 1;
+
+        if fs::symlink_metadata(files_dir.join(&trashed_name)).is_ok() {
+            continue;
+        }
+
+        let mut info_name = trashed_name.clone();
+        info_name.push(INFO_EXT);
+        let info_path = info_dir.join(info_name);
+        let mut info_file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        };
+
+        let result = info_file
+            .write_all(info.as_bytes())
+            .and_then(|_| fs::rename(&path, files_dir.join(&trashed_name)));
+        if let Err(err) = result {
+            let _ = fs::remove_file(&info_path);
+            return Err(err);
+        }
+        return Ok(());
+    }
+}
+
+// returns the trash dir for `path`, and the top dir of its volume if
+// it's not the home trash.
+fn trash_dir_for(path: &Path, home_trash: &Path) -> io::Result<(PathBuf, Option<PathBuf>)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+        let parent = path.parent().unwrap_or(path);
+        let dev = fs::metadata(parent)?.dev();
+        let home_trash_dev = home_trash
+            .ancestors()
+            .find_map(|p| fs::metadata(p).ok())
+            .map(|m| m.dev());
+        if home_trash_dev == Some(dev) {
+            return Ok((home_trash.to_path_buf(), None));
+        }
+
+        let topdir = topdir(parent, dev);
+        let uid = unsafe { libc::getuid() };
+
+        // an admin created $topdir/.Trash, it must have the sticky bit set
+        // and not be a symlink.
+        let shared = topdir.join(".Trash");
+        if let Ok(metadata) = fs::symlink_metadata(&shared) {
+            if metadata.is_dir() && metadata.permissions().mode() & 0o1000 != 0 {
+                let trash_dir = shared.join(uid.to_string());
+                if fs::DirBuilder::new()
+                    .recursive(true)
+                    .mode(0o700)
+                    .create(&trash_dir)
+                    .is_ok()
+                {
+                    return Ok((trash_dir, Some(topdir)));
+                }
+            }
+        }
+
+        let trash_dir = topdir.join(format!(".Trash-{}", uid));
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&trash_dir)?;
+        return Ok((trash_dir, Some(topdir)));
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        return Ok((home_trash.to_path_buf(), None));
+    }
+}
+
+// returns the mount point of the volume `dev` that contains `path`
+#[cfg(unix)]
+fn topdir(path: &Path, dev: u64) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut topdir = path.to_path_buf();
+    for ancestor in path.ancestors() {
+        match fs::metadata(ancestor) {
+            Ok(metadata) if metadata.dev() == dev => topdir = ancestor.to_path_buf(),
+            _ => break,
+        }
+    }
+    return topdir;
+}
+
+// returns the top dir that relative paths in the trash dir's info files
+// are relative to. None for the home trash, which uses absolute paths.
+fn topdir_of(trash_dir: &Path) -> Option<PathBuf> {
+    let parent = trash_dir.parent()?;
+    if parent.file_name() == Some(".Trash".as_ref()) {
+        return parent.parent().map(Path::to_path_buf);
+    }
+    let name = trash_dir.file_name()?.to_string_lossy();
+    if name.starts_with(".Trash-") {
+        return Some(parent.to_path_buf());
+    }
+    return None;
+}
+
+// returns the existing trash dirs: the home trash and the trash dirs
+// of every mounted volume.
+pub fn trash_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = home_trash().into_iter().collect();
+
+    #[cfg(target_os = "linux")]
+    {
+        let uid = unsafe { libc::getuid() };
+        let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+        for line in mounts.lines() {
+            let mount_point = match line.split(' ').nth(1) {
+                Some(mount_point) => PathBuf::from(unescape_mount_point(mount_point)),
+                None => continue,
+            };
+            dirs.push(mount_point.join(".Trash").join(uid.to_string()));
+            dirs.push(mount_point.join(format!(".Trash-{}", uid)));
+        }
+    }
+
+    let mut existing: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if dir.is_dir() && !existing.contains(&dir) {
+            existing.push(dir);
+        }
+    }
+    return existing;
+}
+
+// mount points in /proc/self/mounts escape spaces and such as octal, e.g. `\040`
+#[cfg(target_os = "linux")]
+fn unescape_mount_point(mount_point: &str) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+
+    let bytes = mount_point.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let octal = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(octal, 8) {
+                unescaped.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        unescaped.push(bytes[i]);
+        i += 1;
+    }
+    return OsString::from_vec(unescaped);
+}
+
+// lists the items of every trash dir
+pub fn list_all() -> io::Result<Vec<TrashItem>> {
+    let mut items = Vec::new();
+    for trash_dir in trash_dirs() {
+        items.append(&mut list(&trash_dir)?);
+    }
+    return Ok(items);
+}
+
+// lists the items in `trash_dir`. Items without an info file, or whose info
+// file can't be parsed, are skipped.
+pub fn list(trash_dir: &Path) -> io::Result<Vec<TrashItem>> {
+    let info_dir = trash_dir.join("info");
+    if !info_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let topdir = topdir_of(trash_dir);
+    let mut items = Vec::new();
+    for entry in fs::read_dir(info_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if !file_name.to_string_lossy().ends_with(INFO_EXT) {
+            continue;
+        }
+        // strip the extension while keeping non UTF-8 names intact
+        let name = Path::new(&file_name)
+            .file_stem()
+            .unwrap_or_default()
+            .to_os_string();
+
+        let info = match fs::read_to_string(entry.path()) {
+            Ok(info) => info,
+            Err(_) => continue,
+        };
+        let (original_path, deletion_date) = match parse_info(&info) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        let original_path = match &topdir {
+            Some(topdir) if original_path.is_relative() => topdir.join(original_path),
+            _ => original_path,
+        };
+
+        let mut item = TrashItem {
+            trash_dir: trash_dir.to_path_buf(),
+            name,
+            original_path,
+            deletion_date,
+            is_dir: false,
+        };
+        if let Ok(metadata) = fs::symlink_metadata(item.path()) {
+            item.is_dir = metadata.is_dir();
+            items.push(item);
+        }
+    }
+    return Ok(items);
+}
+
+// returns the original path and deletion date in a .trashinfo file
+fn parse_info(info: &str) -> Option<(PathBuf, String)> {
+    let mut lines = info.lines().map(str::trim);
+    if lines.next()? != "[Trash Info]" {
+        return None;
+    }
+
+    let mut path = None;
+    let mut deletion_date = String::new();
+    for line in lines {
+        if let Some(value) = line.strip_prefix("Path=") {
+            path = Some(utils::percent_decode_path(value));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            deletion_date = value.replace('T', " ");
+        } else if line.starts_with('[') {
+            break;
+        }
+    }
+    return path.map(|path| (path, deletion_date));
+}
+
+// returns the most recently trashed item that was at `path`
+pub fn find_trashed(path: &Path) -> io::Result<TrashItem> {
+    let not_found = || {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} isn't in the trash", path.display()),
+        )
+    };
+    // the trash stores canonical paths, see `trash_in`
+    let name = path.file_name().ok_or_else(not_found)?;
+    let parent = path.parent().ok_or_else(not_found)?;
+    let path = fs::canonicalize(parent)?.join(name);
+
+    return list_all()?
+        .into_iter()
+        .filter(|item| item.original_path == path)
+        .max_by(|a, b| a.deletion_date.cmp(&b.deletion_date))
+        .ok_or_else(not_found);
+}
+
+// moves the item back to where it was deleted from
+pub fn restore(item: &TrashItem) -> io::Result<()> {
+    if fs::symlink_metadata(&item.original_path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", item.original_path.display()),
+        ));
+    }
+    if let Some(parent) = item.original_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(item.path(), &item.original_path)?;
+    return fs::remove_file(item.info_path());
+}
+
+// removes the info file of an item whose entry was already deleted
+pub fn forget(item: &TrashItem) -> io::Result<()> {
+    return fs::remove_file(item.info_path());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_trash_and_restore() {
+        let dir = tempdir().unwrap();
+        let home_trash = dir.path().join("Trash");
+        let file = dir.path().join("a file.txt");
+        fs::write(&file, "a").unwrap();
+
+        trash_in(&file, &home_trash).unwrap();
+        assert!(!file.exists());
+
+        let items = list(&home_trash).unwrap();
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.name, "a file.txt");
+        assert_eq!(
+            item.original_path,
+            fs::canonicalize(dir.path()).unwrap().join("a file.txt")
+        );
+        assert_eq!(fs::read_to_string(item.path()).unwrap(), "a");
+
+        let info = fs::read_to_string(item.info_path()).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("a%20file.txt\n"));
+
+        restore(item).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "a");
+        assert!(list(&home_trash).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash_name_collision() {
+        let dir = tempdir().unwrap();
+        let home_trash = dir.path().join("Trash");
+        let file = dir.path().join("a.txt");
+
+        fs::write(&file, "1").unwrap();
+        trash_in(&file, &home_trash).unwrap();
+        fs::write(&file, "2").unwrap();
+        trash_in(&file, &home_trash).unwrap();
+
+        let mut items = list(&home_trash).unwrap();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<&OsString> = items.iter().map(|i| &i.name).collect();
+        assert_eq!(names, vec!["a.txt", "a.txt.2"]);
+        assert_eq!(fs::read_to_string(items[1].path()).unwrap(), "2");
+
+        // restoring onto an existing file fails and keeps the item
+        fs::write(&file, "3").unwrap();
+        assert!(restore(&items[0]).is_err());
+        assert_eq!(list(&home_trash).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let (path, date) =
+            parse_info("[Trash Info]\nPath=foo/b%C3%A7r\nDeletionDate=2004-08-31T22:32:08\n")
+                .unwrap();
+        assert_eq!(path, PathBuf::from("foo/bçr"));
+        assert_eq!(date, "2004-08-31 22:32:08");
+
+        assert!(parse_info("Path=/a").is_none());
+        assert_eq!(
+            topdir_of(Path::new("/mnt/usb/.Trash-1000")),
+            Some(PathBuf::from("/mnt/usb"))
+        );
+        assert_eq!(
+            topdir_of(Path::new("/mnt/usb/.Trash/1000")),
+            Some(PathBuf::from("/mnt/usb"))
+        );
+        assert_eq!(topdir_of(Path::new("/home/u/.local/share/Trash")), None);
+    }
+}
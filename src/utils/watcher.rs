@@ -0,0 +1,364 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+
+// DirChange is a change to an entry of a watched dir
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DirChange {
+    Created(PathBuf),
+    Deleted(PathBuf),
+    Modified(PathBuf),
+    // from, to. Both are in the same dir.
+    Renamed(PathBuf, PathBuf),
+    // the dir itself was deleted or moved, or its events were lost. It
+    // has to be listed again.
+    Reload(PathBuf),
+}
+
+impl DirChange {
+    // returns the dir where the change happened
+    pub fn dir(&self) -> Option<&std::path::Path> {
+        return match self {
+            DirChange::Created(path)
+            | DirChange::Deleted(path)
+            | DirChange::Modified(path)
+            | DirChange::Renamed(path, _) => path.parent(),
+            DirChange::Reload(dir) => Some(dir),
+        };
+    }
+}
+
+// Watcher reports the changes to the entries of the watched dirs. The
+// changes are read on a worker thread and sent to `receiver`, calling
+// `wake` after each batch.
+pub struct Watcher {
+    pub receiver: Receiver<DirChange>,
+    watched: HashSet<PathBuf>,
+    #[cfg(target_os = "linux")]
+    inotify: inotify::Inotify,
+}
+
+impl Watcher {
+    #[cfg(target_os = "linux")]
+    pub fn new(wake: impl Fn() + Send + 'static) -> io::Result<Self> {
+        let (inotify, receiver) = inotify::Inotify::start(Box::new(wake))?;
+        return Ok(Self {
+            receiver,
+            watched: HashSet::new(),
+            inotify,
+        });
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn new(_wake: impl Fn() + Send + 'static) -> io::Result<Self> {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "watching dirs is only supported on Linux",
+        ));
+    }
+
+    // watches exactly the given dirs, dropping the others. Dirs that
+    // can't be watched aren't retried until they're dropped.
+    pub fn watch_only(&mut self, dirs: HashSet<PathBuf>) -> io::Result<()> {
+        if dirs == self.watched {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        #[cfg(target_os = "linux")]
+        {
+            for dir in self.watched.difference(&dirs) {
+                self.inotify.remove(dir);
+            }
+            for dir in dirs.difference(&self.watched) {
+                if let Err(err) = self.inotify.add(dir) {
+                    result = Err(err);
+                }
+            }
+        }
+        self.watched = dirs;
+        return result;
+    }
+
+    // returns the changes since the last call
+    pub fn poll(&self) -> Vec<DirChange> {
+        let mut changes: Vec<DirChange> = Vec::new();
+        for change in self.receiver.try_iter() {
+            // writing a file sends many modifications in a row
+            if changes.last() != Some(&change) {
+                changes.push(change);
+            }
+        }
+        return changes;
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::collections::HashMap;
+    use std::ffi::{CString, OsStr};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::DirChange;
+
+    const MASK: u32 = libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MODIFY
+        | libc::IN_ATTRIB
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_DELETE_SELF
+        | libc::IN_MOVE_SELF
+        | libc::IN_ONLYDIR;
+    // how long the reader waits for events before checking if it stopped
+    const POLL_TIMEOUT_MS: i32 = 200;
+
+    // Inotify owns the inotify instance and the thread reading its events
+    pub struct Inotify {
+        fd: i32,
+        // the watched dirs by watch descriptor, shared with the reader
+        dirs: Arc<Mutex<HashMap<i32, PathBuf>>>,
+        stopped: Arc<AtomicBool>,
+    }
+
+    impl Inotify {
+        pub fn start(wake: Box<dyn Fn() + Send>) -> io::Result<(Self, Receiver<DirChange>)> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let (sender, receiver) = mpsc::channel();
+            let dirs = Arc::new(Mutex::new(HashMap::new()));
+            let stopped = Arc::new(AtomicBool::new(false));
+
+            let reader_dirs = dirs.clone();
+            let reader_stopped = stopped.clone();
+            thread::spawn(move || read_events(fd, reader_dirs, reader_stopped, sender, wake));
+
+            return Ok((Self { fd, dirs, stopped }, receiver));
+        }
+
+        pub fn add(&self, dir: &Path) -> io::Result<()> {
+            let path = CString::new(dir.as_os_str().as_bytes())?;
+            let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), MASK) };
+            if wd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if let Ok(mut dirs) = self.dirs.lock() {
+                dirs.insert(wd, dir.to_path_buf());
+            }
+            return Ok(());
+        }
+
+        pub fn remove(&self, dir: &Path) {
+            let mut dirs = match self.dirs.lock() {
+                Ok(dirs) => dirs,
+                Err(_) => return,
+            };
+            let wd = dirs
+                .iter()
+                .find(|(_, d)| d.as_path() == dir)
+                .map(|(wd, _)| *wd);
+            if let Some(wd) = wd {
+                dirs.remove(&wd);
+                unsafe { libc::inotify_rm_watch(self.fd, wd) };
+            }
+        }
+    }
+
+    impl Drop for Inotify {
+        // the reader closes the fd once it sees this
+        fn drop(&mut self) {
+            self.stopped.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn read_events(
+        fd: i32,
+        dirs: Arc<Mutex<HashMap<i32, PathBuf>>>,
+        stopped: Arc<AtomicBool>,
+        sender: Sender<DirChange>,
+        wake: Box<dyn Fn() + Send>,
+    ) {
+        let mut buf = vec![0u8; 64 * 1024];
+        while !stopped.load(Ordering::Relaxed) {
+            let mut pollfd = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ready = unsafe { libc::poll(&mut pollfd, 1, POLL_TIMEOUT_MS) };
+            if ready <= 0 {
+                continue;
+            }
+            let len = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if len <= 0 {
+                continue;
+            }
+
+            let changes = match dirs.lock() {
+                Ok(dirs) => parse_events(&buf[..len as usize], &dirs),
+                Err(_) => break,
+            };
+            if changes.is_empty() {
+                continue;
+            }
+            for change in changes {
+                if sender.send(change).is_err() {
+                    stopped.store(true, Ordering::Relaxed);
+                }
+            }
+            wake();
+        }
+        unsafe { libc::close(fd) };
+    }
+
+    // turns the raw events into changes. A move out of a dir followed by
+    // a move into the same dir is a rename.
+    pub(super) fn parse_events(buf: &[u8], dirs: &HashMap<i32, PathBuf>) -> Vec<DirChange> {
+        let header_len = std::mem::size_of::<libc::inotify_event>();
+        let mut changes = Vec::new();
+        // the path moved out of its dir, by cookie
+        let mut moved_from: HashMap<u32, (PathBuf, usize)> = HashMap::new();
+        let mut offset = 0;
+        while offset + header_len <= buf.len() {
+            let event: libc::inotify_event =
+                unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr() as *const _) };
+            let name_start = offset + header_len;
+            let name_end = (name_start + event.len as usize).min(buf.len());
+            offset = name_end;
+
+            // the queue overflowed, some events of any dir may be lost
+            if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                changes.extend(dirs.values().map(|dir| DirChange::Reload(dir.clone())));
+                continue;
+            }
+            let dir = match dirs.get(&event.wd) {
+                Some(dir) => dir,
+                None => continue,
+            };
+            if event.mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF) != 0 {
+                changes.push(DirChange::Reload(dir.clone()));
+                continue;
+            }
+            // the name is padded with NULs
+            let name = &buf[name_start..name_end];
+            let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+            if name.is_empty() {
+                continue;
+            }
+            let path = dir.join(OsStr::from_bytes(name));
+
+            let mask = event.mask;
+            if mask & libc::IN_MOVED_FROM != 0 {
+                moved_from.insert(event.cookie, (path.clone(), changes.len()));
+                changes.push(DirChange::Deleted(path));
+            } else if mask & libc::IN_MOVED_TO != 0 {
+                match moved_from.remove(&event.cookie) {
+                    Some((from, index)) if from.parent() == path.parent() => {
+                        changes[index] = DirChange::Renamed(from, path);
+                    }
+                    _ => changes.push(DirChange::Created(path)),
+                }
+            } else if mask & libc::IN_CREATE != 0 {
+                changes.push(DirChange::Created(path));
+            } else if mask & libc::IN_DELETE != 0 {
+                changes.push(DirChange::Deleted(path));
+            } else if mask & (libc::IN_MODIFY | libc::IN_ATTRIB) != 0 {
+                changes.push(DirChange::Modified(path));
+            }
+        }
+        return changes;
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    // waits until the watcher reports `count` changes
+    fn wait_changes(watcher: &Watcher, count: usize) -> Vec<DirChange> {
+        let mut changes = Vec::new();
+        let start = Instant::now();
+        while changes.len() < count && start.elapsed() < Duration::from_secs(5) {
+            changes.extend(watcher.poll());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        return changes;
+    }
+
+    #[test]
+    fn test_watcher() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let mut watcher = Watcher::new(|| {}).unwrap();
+        watcher.watch_only(HashSet::from([root.clone()])).unwrap();
+
+        fs::write(root.join("a"), "").unwrap();
+        assert_eq!(
+            wait_changes(&watcher, 1)[0],
+            DirChange::Created(root.join("a"))
+        );
+
+        fs::rename(root.join("a"), root.join("b")).unwrap();
+        fs::remove_file(root.join("b")).unwrap();
+        assert_eq!(
+            wait_changes(&watcher, 2),
+            vec![
+                DirChange::Renamed(root.join("a"), root.join("b")),
+                DirChange::Deleted(root.join("b")),
+            ]
+        );
+
+        // a watched dir that goes away is listed again
+        let sub = root.join("sub");
+        fs::create_dir(&sub).unwrap();
+        watcher
+            .watch_only(HashSet::from([root.clone(), sub.clone()]))
+            .unwrap();
+        fs::remove_dir(&sub).unwrap();
+        assert!(wait_changes(&watcher, 2).contains(&DirChange::Reload(sub)));
+
+        // unwatched dirs aren't reported
+        watcher.watch_only(HashSet::new()).unwrap();
+        fs::write(root.join("c"), "").unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(watcher.poll().is_empty());
+    }
+
+    #[test]
+    fn test_queue_overflow() {
+        let event = libc::inotify_event {
+            wd: -1,
+            mask: libc::IN_Q_OVERFLOW,
+            cookie: 0,
+            len: 0,
+        };
+        let buf = unsafe {
+            std::slice::from_raw_parts(
+                &event as *const libc::inotify_event as *const u8,
+                std::mem::size_of::<libc::inotify_event>(),
+            )
+        };
+        let dirs = HashMap::from([(1, PathBuf::from("/a")), (2, PathBuf::from("/b"))]);
+        let mut changes = inotify::parse_events(buf, &dirs);
+        changes.sort_by_key(|change| format!("{:?}", change));
+        assert_eq!(
+            changes,
+            vec![
+                DirChange::Reload(PathBuf::from("/a")),
+                DirChange::Reload(PathBuf::from("/b")),
+            ]
+        );
+    }
+}